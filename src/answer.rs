@@ -5,6 +5,20 @@ use std::ops::{Index, IndexMut};
 pub const UNFILLED: (i32, i32) = (-1, -1);
 pub const BLOCKED: (i32, i32) = (-2, -2);
 
+/// A piece instance of an answer, given as a transformation and a translation of the piece.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    /// Index of the piece in `Puzzle::pieces`.
+    pub piece: usize,
+    /// Number of the copy of the piece, i.e. the instance covers the cells with the value `(piece, copy)`.
+    pub copy: i32,
+    pub transformation: Transformation,
+    /// Position of the transformed piece in the answer, in the same way as `FixedPlacement::offset`.
+    pub offset: Coord,
+    /// Cells covered by the instance, sorted.
+    pub cells: Vec<Coord>,
+}
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct Answer {
     size: Coord,
@@ -18,7 +32,7 @@ impl Answer {
             data: vec![UNFILLED; (size.x * size.y * size.z) as usize],
         }
     }
-    pub fn from_answer<T: Bits>(dic: &Dictionary<T>, answer_raw: &Vec<(i32, i32)>) -> Answer {
+    pub fn from_answer<T: Bits>(dic: &Dictionary<T>, answer_raw: &[(i32, i32)]) -> Answer {
         let mut n_piece_used = vec![0; dic.piece_count.len()];
         let mut ret = Answer::new(dic.target.size());
 
@@ -38,11 +52,109 @@ impl Answer {
             }
         }
 
+        // cells are not necessarily numbered in the order of coordinates
+        let rem_piece = (0..n_piece_used.len()).map(|i| dic.piece_count[i] - n_piece_used[i]).collect::<Vec<_>>();
+        ret.reindex(&dic.piece_count, &rem_piece);
+
         ret
     }
+    /// Returns `true` if `self` is the representative of its equivalence class under `symmetry`,
+    /// i.e. no transformation in `symmetry` yields a smaller answer.
+    ///
+    /// `rem_piece` is the number of pieces left unused in `self`.
+    pub fn is_canonical<T: Bits>(&self, dic: &Dictionary<T>, symmetry: Symmetry, rem_piece: &[i32]) -> bool {
+        for i in 1..48 {
+            if (symmetry & (1u64 << i)) == 0 { continue; }
+
+            if let Some(answer_rot) = self.trans_answer(dic, i, rem_piece) {
+                if *self > answer_rot {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+    /// Returns the number of distinct answers obtained from `self` by the transformations in `symmetry`,
+    /// including `self` itself.
+    ///
+    /// `rem_piece` is the number of pieces left unused in `self`.
+    pub fn orbit_size<T: Bits>(&self, dic: &Dictionary<T>, symmetry: Symmetry, rem_piece: &[i32]) -> u64 {
+        let mut orbit = vec![self.clone()];
+        for i in 1..48 {
+            if (symmetry & (1u64 << i)) == 0 { continue; }
+
+            if let Some(answer_rot) = self.trans_answer(dic, i, rem_piece) {
+                orbit.push(answer_rot);
+            }
+        }
+        orbit.sort();
+        orbit.dedup();
+        orbit.len() as u64
+    }
+    /// Returns `self` transformed by `TRANSFORMATIONS[i]`, or `None` if the transformed answer is not an answer
+    /// because the pieces used do not turn into available ones.
+    fn trans_answer<T: Bits>(&self, dic: &Dictionary<T>, i: usize, rem_piece: &[i32]) -> Option<Answer> {
+        // the transformed answer must be an answer as well, using the pieces each piece turns into
+        let map = &dic.piece_map[i];
+        let mut rem_piece_trans = dic.piece_count.clone();
+        for j in 0..rem_piece.len() {
+            let n_used = dic.piece_count[j] - rem_piece[j];
+            if n_used == 0 { continue; }
+            if map[j] < 0 || rem_piece_trans[map[j] as usize] < n_used {
+                return None;
+            }
+            rem_piece_trans[map[j] as usize] -= n_used;
+        }
+
+        let mut answer_rot = self.trans(TRANSFORMATIONS[i]);
+        answer_rot.map_pieces(map);
+        answer_rot.reindex(&dic.piece_count, &rem_piece_trans);
+        Some(answer_rot)
+    }
     pub fn size(&self) -> Coord {
         self.size
     }
+    /// Returns the piece instances of `self`, an answer of `puzzle`, sorted by the piece and the copy.
+    ///
+    /// If the piece has symmetries, the first matching transformation of its orientation policy is chosen
+    /// (falling back to all the transformations, which fixed placements may use).
+    /// Returns `None` if some instance is not a transformation of its piece.
+    pub fn placements(&self, puzzle: &Puzzle) -> Option<Vec<Placement>> {
+        let mut instances: Vec<((i32, i32), Vec<Coord>)> = vec![];
+        for cd in self.size {
+            let (piece, copy) = self[cd];
+            if piece < 0 { continue; }
+            match instances.iter_mut().find(|inst| inst.0 == (piece, copy)) {
+                Some(inst) => inst.1.push(cd),
+                None => instances.push(((piece, copy), vec![cd])),
+            }
+        }
+        instances.sort();
+
+        let mut ret = vec![];
+        for ((piece, copy), cells) in instances {
+            let piece = piece as usize;
+            let shape = &puzzle.pieces.get(piece)?.0;
+            let mut candidates = puzzle.orientations.get(piece).map_or(vec![], |policy| policy.transformations());
+            candidates.extend_from_slice(&TRANSFORMATIONS);
+
+            let mut found = None;
+            for &t in &candidates {
+                let moved = shape.trans(t);
+                let moved_cells = moved.size().into_iter().filter(|&cd| moved.get(cd)).collect::<Vec<_>>();
+                if moved_cells.len() != cells.len() { continue; }
+                let offset = cells[0] - moved_cells[0];
+                if (0..cells.len()).all(|i| moved_cells[i] + offset == cells[i]) {
+                    found = Some((t, offset));
+                    break;
+                }
+            }
+            let (transformation, offset) = found?;
+            ret.push(Placement { piece, copy, transformation, offset, cells });
+        }
+        Some(ret)
+    }
     fn coord(&self, c: Coord) -> usize {
         ((c.x * self.size.y + c.y) * self.size.z + c.z) as usize
     }
@@ -53,12 +165,15 @@ impl Answer {
         }
         ret
     }
-    pub fn mirror(&mut self, mirror_pair: &Vec<i32>) {
+    /// Replaces each piece `p` with `map[p]`.
+    pub fn map_pieces(&mut self, map: &[i32]) {
         for d in &mut self.data {
-            d.0 = mirror_pair[d.0 as usize];
+            if d.0 >= 0 {
+                d.0 = map[d.0 as usize];
+            }
         }
     }
-    pub fn reindex(&mut self, total_piece_count: &Vec<i32>, rem_piece: &Vec<i32>) {
+    pub fn reindex(&mut self, total_piece_count: &[i32], rem_piece: &[i32]) {
         let mut ofs = vec![0; total_piece_count.len()];
         for i in 1..total_piece_count.len() {
             ofs[i] = ofs[i - 1] + (total_piece_count[i - 1] - rem_piece[i - 1]);
@@ -68,6 +183,7 @@ impl Answer {
         let mut new_idx = vec![-1; total_pieces_used as usize];
 
         for pn in &mut self.data {
+            if pn.0 < 0 { continue; }
             let p = pn.0 as usize;
             let idx_orig = (ofs[p] + pn.1) as usize;
             if new_idx[idx_orig] == -1 {
@@ -95,11 +211,40 @@ impl IndexMut<Coord> for Answer {
     }
 }
 
+/// The reason why a search finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchStatus {
+    /// The whole search space was explored.
+    Completed,
+    /// The answer callback requested to stop.
+    Stopped,
+    /// `SolveOptions::deadline` was passed.
+    TimedOut,
+    /// `SolveOptions::max_search_steps` was reached.
+    StepLimitReached,
+    /// `SolveOptions::cancel` was set.
+    Cancelled,
+}
+
 pub struct Answers {
     pub answer: Vec<Answer>,
     pub count: u64,
-    pub save_limit: Option<usize>,
     pub search_steps: u64,
+    pub status: SearchStatus,
+    /// The value of the answers for a packing objective (`SolveOptions::objective`), including fixed pieces.
+    pub optimum: Option<i32>,
+    /// The number of answers including the ones equivalent under the symmetries of the target,
+    /// if `SolveOptions::count_symmetric` is set.
+    pub raw_count: Option<u64>,
+    /// The number of answers equivalent to each of `answer` (including itself),
+    /// if `SolveOptions::count_symmetric` is set.
+    pub orbit_size: Vec<u64>,
+}
+
+impl Default for Answers {
+    fn default() -> Answers {
+        Answers::new()
+    }
 }
 
 impl Answers {
@@ -107,8 +252,69 @@ impl Answers {
         Answers {
             answer: vec![],
             count: 0u64,
-            save_limit: None,
             search_steps: 0u64,
+            status: SearchStatus::Completed,
+            optimum: None,
+            raw_count: None,
+            orbit_size: vec![],
+        }
+    }
+    /// Returns `true` if the search was not cut short, i.e. `count` is the exact number of answers.
+    pub fn is_completed(&self) -> bool {
+        self.status == SearchStatus::Completed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placements() {
+        let pieces = library::pentominoes().into_iter().map(|(_, shape, count)| (shape, count)).collect::<Vec<_>>();
+        let mut puzzle = Puzzle::new(pieces, Shape::filled(Coord { x: 20, y: 3, z: 1 }));
+        let answers = solve(&puzzle).unwrap();
+        for answer in &answers.answer {
+            let placements = answer.placements(&puzzle).unwrap();
+            assert_eq!(placements.len(), 12);
+
+            // the placements rebuild the answer
+            let mut rebuilt = Answer::new(answer.size());
+            for (i, pl) in placements.iter().enumerate() {
+                assert_eq!((pl.piece, pl.copy), (i, 0));
+                let shape = puzzle.pieces[pl.piece].0.trans(pl.transformation);
+                for cd in shape.size() {
+                    if shape.get(cd) {
+                        rebuilt[cd + pl.offset] = (pl.piece as i32, pl.copy);
+                    }
+                }
+                assert_eq!(pl.cells.len(), 5);
+                assert!(pl.cells.iter().all(|&cd| answer[cd] == (i as i32, 0)));
+            }
+            assert!(rebuilt == *answer);
         }
+
+        // fixed placements are recovered, and blocked cells are skipped
+        let domino = Shape::filled(Coord { x: 2, y: 1, z: 1 });
+        puzzle = Puzzle::new(vec![(domino.clone(), 4)], Shape::filled(Coord { x: 3, y: 3, z: 1 }));
+        puzzle.blocked.push(Coord { x: 2, y: 2, z: 0 });
+        let turned = Transformation::id().rotate_z_axis();
+        puzzle.fixed.push(FixedPlacement { piece: 0, transformation: turned, offset: Coord { x: 0, y: 1, z: 0 } });
+        let answers = solve(&puzzle).unwrap();
+        assert!(answers.count > 0);
+        for answer in &answers.answer {
+            let placements = answer.placements(&puzzle).unwrap();
+            assert_eq!(placements.len(), 4);
+            let fixed = placements.iter().find(|pl| pl.offset == Coord { x: 0, y: 1, z: 0 }).unwrap();
+            assert!(domino.trans(fixed.transformation) == domino.trans(turned));
+            assert_eq!(fixed.cells, vec![Coord { x: 0, y: 1, z: 0 }, Coord { x: 0, y: 2, z: 0 }]);
+        }
+
+        // not an answer of the puzzle
+        let mut answer = Answer::new(Coord { x: 3, y: 3, z: 1 });
+        answer[Coord { x: 0, y: 0, z: 0 }] = (0, 0);
+        assert!(answer.placements(&puzzle).is_none());
+        answer[Coord { x: 0, y: 0, z: 0 }] = (1, 0);
+        assert!(answer.placements(&puzzle).is_none());
     }
 }
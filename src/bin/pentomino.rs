@@ -1,5 +1,7 @@
 extern crate polymate;
 use polymate::*;
+use std::env;
+use std::process;
 use std::time::Instant;
 
 fn main() {
@@ -58,23 +60,32 @@ fn main() {
             ".##",
         ],
     ];
-    let pieces = pieces_base.into_iter().map(|g| (Shape::from_grid(&g), 1)).collect::<Vec<_>>();
+    let pieces = pieces_base.into_iter().map(|g| (Shape::from_grid(&g).unwrap(), 1)).collect::<Vec<_>>();
     let target = Shape::filled(Coord { x: 10, y: 6, z: 1 });
 
-    let problem = Puzzle { pieces, target };
+    let problem = Puzzle::new(pieces, target);
+
+    let mut options = SolveOptions::new();
+    if let Some(threads) = env::args().nth(1) {
+        options.threads = threads.parse().expect("number of threads expected");
+    }
 
     let start = Instant::now();
-    let ans = solve(&problem);
+    let ans = match solve_with_options(&problem, &options) {
+        Ok(ans) => ans,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    };
     let end = start.elapsed();
     
-    println!("Solution: {} (Cost: {}.{:03}[s])", ans.count, end.as_secs(), end.subsec_nanos() / 1000000);
+    println!("Solution: {} (Cost: {}.{:03}[s])", ans.count, end.as_secs(), end.subsec_millis());
     println!("Steps: {}", ans.search_steps);
+    let ans1 = match ans.answer.first() {
+        Some(a) => a,
+        None => return,
+    };
     println!("First answer:");
-    let ans1 = &ans.answer[0];
-    for y in 0..ans1.size().y {
-        for x in 0..ans1.size().x {
-            print!("{:2} ", ans1[Coord { x, y, z: 0 }].0);
-        }
-        println!();
-    }
+    print!("{}", ans1);
 }
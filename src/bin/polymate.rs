@@ -0,0 +1,349 @@
+extern crate polymate;
+use polymate::*;
+use std::env;
+use std::fs;
+use std::ops::ControlFlow;
+use std::process;
+
+const USAGE: &str = "\
+usage: polymate <command> [options] <puzzle file>
+
+commands:
+    count     count the answers
+    solve     print the answers
+    render    print the pieces and the target
+    info      print orientations and placements of the pieces and the symmetry of the target
+
+options:
+    --backend <auto|bitmask|most-constrained|dancing-links>
+    --threads <n>        number of threads (0 for all cores; default 1)
+    --limit <n>          stop after finding n answers (solve only)
+    --objective <exact|max-pieces|max-volume>
+                         find tilings (default) or packings leaving cells empty
+    --count-symmetric    also count the answers equivalent under the symmetries of the target
+    --format <text|json> output format (default text)
+    --ascii              draw text output with ASCII characters instead of box-drawing ones";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+}
+
+struct Args {
+    command: String,
+    file: String,
+    options: SolveOptions,
+    limit: Option<u64>,
+    format: Format,
+    ascii: bool,
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("error: {}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn parse_args() -> Args {
+    let mut command = None;
+    let mut file = None;
+    let mut options = SolveOptions::new();
+    let mut limit = None;
+    let mut format = Format::Text;
+    let mut ascii = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            if command.is_none() {
+                command = Some(arg);
+            } else if file.is_none() {
+                file = Some(arg);
+            } else {
+                usage_error(&format!("unexpected argument `{}`", arg));
+            }
+            continue;
+        }
+        if arg == "--count-symmetric" {
+            options.count_symmetric = true;
+            continue;
+        }
+        if arg == "--ascii" {
+            ascii = true;
+            continue;
+        }
+        let value = match args.next() {
+            Some(value) => value,
+            None => usage_error(&format!("`{}` requires a value", arg)),
+        };
+        match &arg[..] {
+            "--backend" => {
+                options.backend = match &value[..] {
+                    "auto" => Backend::Auto,
+                    "bitmask" => Backend::Bitmask,
+                    "most-constrained" => Backend::MostConstrained,
+                    "dancing-links" => Backend::DancingLinks,
+                    _ => usage_error(&format!("unknown backend `{}`", value)),
+                }
+            }
+            "--threads" => {
+                options.threads = value.parse().unwrap_or_else(|_| usage_error("`--threads` requires a number"));
+            }
+            "--objective" => {
+                options.objective = match &value[..] {
+                    "exact" => Objective::ExactCover,
+                    "max-pieces" => Objective::MaxPieces,
+                    "max-volume" => Objective::MaxVolume,
+                    _ => usage_error(&format!("unknown objective `{}`", value)),
+                }
+            }
+            "--limit" => {
+                limit = Some(value.parse().unwrap_or_else(|_| usage_error("`--limit` requires a number")));
+            }
+            "--format" => {
+                format = match &value[..] {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    _ => usage_error(&format!("unknown format `{}`", value)),
+                }
+            }
+            _ => usage_error(&format!("unknown option `{}`", arg)),
+        }
+    }
+
+    match (command, file) {
+        (Some(command), Some(file)) => {
+            if !["count", "solve", "render", "info"].contains(&&command[..]) {
+                usage_error(&format!("unknown command `{}`", command));
+            }
+            if limit.is_some() && command != "solve" {
+                usage_error("`--limit` is available only for `solve`");
+            }
+            Args { command, file, options, limit, format, ascii }
+        }
+        _ => usage_error("a command and a puzzle file are required"),
+    }
+}
+
+fn fail(e: PolymateError) -> ! {
+    eprintln!("error: {}", e);
+    process::exit(1);
+}
+
+fn json_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+fn json_optimum(answers: &Answers) -> String {
+    answers.optimum.map_or("null".to_string(), |v| v.to_string())
+}
+
+fn json_raw_count(answers: &Answers) -> String {
+    answers.raw_count.map_or("null".to_string(), |v| v.to_string())
+}
+
+/// Returns the name of the piece at each cell of `answer`, indexed by z, y and x; empty cells are `None`.
+fn answer_cells<'a>(puzzle: &'a Puzzle, answer: &Answer) -> Vec<Vec<Vec<Option<&'a str>>>> {
+    let size = answer.size();
+    (0..size.z).map(|z| {
+        (0..size.y).map(|y| {
+            (0..size.x).map(|x| {
+                let (piece, _) = answer[Coord { x, y, z }];
+                if piece >= 0 { Some(&puzzle.piece_names[piece as usize][..]) } else { None }
+            }).collect()
+        }).collect()
+    }).collect()
+}
+
+/// Prints `value` drawn with ASCII characters if `args.ascii` is set.
+fn print_drawing<D: std::fmt::Display>(args: &Args, value: D) {
+    if args.ascii {
+        print!("{:#}", value);
+    } else {
+        print!("{}", value);
+    }
+}
+
+fn json_grid(cells: &[Vec<Vec<Option<&str>>>]) -> String {
+    let layers = cells.iter().map(|layer| {
+        let rows = layer.iter().map(|row| {
+            let row = row.iter().map(|c| c.map_or("null".to_string(), json_string)).collect::<Vec<_>>();
+            format!("[{}]", row.join(","))
+        }).collect::<Vec<_>>();
+        format!("[{}]", rows.join(","))
+    }).collect::<Vec<_>>();
+    format!("[{}]", layers.join(","))
+}
+
+fn shape_cells(shape: &Shape, name: &str) -> Vec<Vec<Vec<Option<String>>>> {
+    let size = shape.size();
+    (0..size.z).map(|z| {
+        (0..size.y).map(|y| {
+            (0..size.x).map(|x| if shape.get(Coord { x, y, z }) { Some(name.to_string()) } else { None }).collect()
+        }).collect()
+    }).collect()
+}
+
+fn borrow_cells(cells: &[Vec<Vec<Option<String>>>]) -> Vec<Vec<Vec<Option<&str>>>> {
+    cells.iter().map(|layer| layer.iter().map(|row| row.iter().map(|c| c.as_deref()).collect()).collect()).collect()
+}
+
+fn print_summary(args: &Args, answers: &Answers, count: u64) {
+    if args.format == Format::Text {
+        if let Some(optimum) = answers.optimum {
+            println!("optimum: {}", optimum);
+        }
+        println!("answers: {}", count);
+        if let Some(raw_count) = answers.raw_count {
+            println!("raw answers: {}", raw_count);
+        }
+        println!("steps: {}", answers.search_steps);
+        println!("status: {:?}", answers.status);
+    }
+}
+
+fn count(args: &Args, puzzle: &Puzzle) {
+    let answers = solve_with(puzzle, &args.options, |_| ControlFlow::Continue(())).unwrap_or_else(|e| fail(e));
+    match args.format {
+        Format::Text => print_summary(args, &answers, answers.count),
+        Format::Json => println!(
+            "{{\"count\":{},\"raw_count\":{},\"search_steps\":{},\"status\":{},\"optimum\":{}}}",
+            answers.count, json_raw_count(&answers), answers.search_steps, json_string(&format!("{:?}", answers.status)), json_optimum(&answers)
+        ),
+    }
+}
+
+fn solve_command(args: &Args, puzzle: &Puzzle) {
+    let mut found = vec![];
+    let limit = args.limit;
+    let answers = if limit == Some(0) {
+        // Nothing is wanted, so only check that the puzzle is well-formed.
+        Dictionary::<Bitset>::with_objective(puzzle, args.options.objective).unwrap_or_else(|e| fail(e));
+        let mut answers = Answers::new();
+        answers.status = SearchStatus::Stopped;
+        answers
+    } else {
+        solve_with(puzzle, &args.options, |a| {
+            if limit.is_some_and(|limit| found.len() as u64 >= limit) {
+                return ControlFlow::Break(());
+            }
+            found.push(a.clone());
+            if limit.is_some_and(|limit| found.len() as u64 >= limit) { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+        }).unwrap_or_else(|e| fail(e))
+    };
+
+    match args.format {
+        Format::Text => {
+            for (i, a) in found.iter().enumerate() {
+                println!("answer #{}", i + 1);
+                print_drawing(args, a.with_names(&puzzle.piece_names));
+                println!();
+            }
+            print_summary(args, &answers, found.len() as u64);
+        }
+        Format::Json => {
+            let list = found.iter().map(|a| json_grid(&answer_cells(puzzle, a))).collect::<Vec<_>>();
+            println!(
+                "{{\"count\":{},\"raw_count\":{},\"search_steps\":{},\"status\":{},\"optimum\":{},\"answers\":[{}]}}",
+                found.len(), json_raw_count(&answers), answers.search_steps, json_string(&format!("{:?}", answers.status)), json_optimum(&answers), list.join(",")
+            );
+        }
+    }
+}
+
+fn render(args: &Args, puzzle: &Puzzle) {
+    match args.format {
+        Format::Text => {
+            for i in 0..puzzle.pieces.len() {
+                let (ref shape, count) = puzzle.pieces[i];
+                let name = &puzzle.piece_names[i];
+                println!("piece {} (x{})", name, count);
+                print_drawing(args, shape);
+                println!();
+            }
+            println!("target");
+            print_drawing(args, &puzzle.target);
+        }
+        Format::Json => {
+            let pieces = (0..puzzle.pieces.len()).map(|i| {
+                let (ref shape, count) = puzzle.pieces[i];
+                let name = &puzzle.piece_names[i];
+                format!(
+                    "{{\"name\":{},\"count\":{},\"shape\":{}}}",
+                    json_string(name), count, json_grid(&borrow_cells(&shape_cells(shape, name)))
+                )
+            }).collect::<Vec<_>>();
+            println!(
+                "{{\"pieces\":[{}],\"target\":{}}}",
+                pieces.join(","), json_grid(&borrow_cells(&shape_cells(&puzzle.target, "#")))
+            );
+        }
+    }
+}
+
+fn info(args: &Args, puzzle: &Puzzle) {
+    let dic = Dictionary::<Bitset>::with_objective(puzzle, args.options.objective).unwrap_or_else(|e| fail(e));
+    let size = puzzle.target.size();
+    let n_symmetries = dic.target_symmetry.count_ones();
+    // Packing objectives always run the packing search, whatever the backend option says.
+    let search = match (args.options.objective, args.options.backend) {
+        (Objective::ExactCover, Backend::Auto) => format!("{:?}", choose_backend(&dic)),
+        (Objective::ExactCover, backend) => format!("{:?}", backend),
+        _ => "Packing".to_string(),
+    };
+
+    let mut pieces = vec![];
+    for i in 0..puzzle.pieces.len() {
+        let (ref shape, count) = puzzle.pieces[i];
+        let n_placements = dic.placements.iter().map(|p| p[i].len()).sum::<usize>();
+        pieces.push((&puzzle.piece_names[i], count, shape.volume(), shape.orientations_under(&puzzle.orientations[i].transformations()).len(), n_placements));
+    }
+
+    match args.format {
+        Format::Text => {
+            println!("target: {}x{}x{}, {} cells, {} symmetries", size.x, size.y, size.z, dic.n_target_cells, n_symmetries);
+            println!("backend: {}", search);
+            println!("pieces:");
+            for (name, count, volume, n_orientations, n_placements) in pieces {
+                println!("  {} x{}: {} cells, {} orientations, {} placements", name, count, volume, n_orientations, n_placements);
+            }
+        }
+        Format::Json => {
+            let pieces = pieces.into_iter().map(|(name, count, volume, n_orientations, n_placements)| format!(
+                "{{\"name\":{},\"count\":{},\"cells\":{},\"orientations\":{},\"placements\":{}}}",
+                json_string(name), count, volume, n_orientations, n_placements
+            )).collect::<Vec<_>>();
+            println!(
+                "{{\"target\":{{\"size\":[{},{},{}],\"cells\":{},\"symmetries\":{}}},\"backend\":{},\"pieces\":[{}]}}",
+                size.x, size.y, size.z, dic.n_target_cells, n_symmetries,
+                json_string(&search), pieces.join(",")
+            );
+        }
+    }
+}
+
+fn main() {
+    let args = parse_args();
+    let text = fs::read_to_string(&args.file).unwrap_or_else(|e| {
+        eprintln!("error: cannot read {}: {}", args.file, e);
+        process::exit(1);
+    });
+    let puzzle = Puzzle::parse(&text).unwrap_or_else(|e| fail(e));
+
+    match &args.command[..] {
+        "count" => count(&args, &puzzle),
+        "solve" => solve_command(&args, &puzzle),
+        "render" => render(&args, &puzzle),
+        _ => info(&args, &puzzle),
+    }
+}
@@ -1,43 +1,253 @@
+use super::*;
+
 pub trait Bits: Clone {
     fn max_capacity() -> i32;
-    fn allocate(size: i32) -> Self;
+    /// Returns `true` if `Self` is a `FixedBits`, i.e. a plain value supporting bitwise operations and shifts.
+    fn is_fixed_width() -> bool;
+    /// Returns a set of `size` bits, all unset, or `PolymateError::CapacityExceeded` if `size` exceeds `max_capacity()`.
+    fn allocate(size: i32) -> Result<Self, PolymateError>;
     fn disjoint(&self, other: &Self) -> bool;
     fn update(&mut self, other: &Self);
+    fn clear(&mut self);
+    fn union_with(&mut self, other: &Self);
+    fn subtract(&mut self, other: &Self);
+    fn count_ones(&self) -> i32;
     fn set(&mut self, idx: i32);
     fn unset(&mut self, idx: i32);
+    fn get(&self, idx: i32) -> bool;
     fn lowest_unset_bit(&self) -> i32;
     fn lowest_set_bit(&self) -> i32;
+    /// Returns the lowest set bit not lower than `idx`, or `max_capacity()` (`size` for `Bitset`) if there is none.
+    fn next_set_bit(&self, idx: i32) -> i32;
     fn is_empty(&self) -> bool;
 }
 
-impl Bits for u64 {
+/// Bit sets of a fixed width, which allow the specialized search for small targets.
+pub trait FixedBits: Bits + Copy {
+    fn or(self, other: Self) -> Self;
+    fn and(self, other: Self) -> Self;
+    fn not(self) -> Self;
+    fn shl(self, n: u32) -> Self;
+    fn shr(self, n: u32) -> Self;
+}
+
+macro_rules! impl_bits_for_primitive {
+    ($t:ty, $w:expr) => {
+        impl Bits for $t {
+            fn max_capacity() -> i32 {
+                $w
+            }
+            fn is_fixed_width() -> bool {
+                true
+            }
+            fn allocate(size: i32) -> Result<$t, PolymateError> {
+                if size > $w {
+                    return Err(PolymateError::CapacityExceeded { cells: size, capacity: $w });
+                }
+                Ok(0)
+            }
+            fn disjoint(&self, other: &$t) -> bool {
+                (*self & *other) == 0
+            }
+            fn update(&mut self, other: &$t) {
+                *self ^= *other;
+            }
+            fn clear(&mut self) {
+                *self = 0;
+            }
+            fn union_with(&mut self, other: &$t) {
+                *self |= *other;
+            }
+            fn subtract(&mut self, other: &$t) {
+                *self &= !*other;
+            }
+            fn count_ones(&self) -> i32 {
+                <$t>::count_ones(*self) as i32
+            }
+            fn set(&mut self, idx: i32) {
+                *self |= 1 << (idx as u32);
+            }
+            fn unset(&mut self, idx: i32) {
+                *self &= !(1 << (idx as u32));
+            }
+            fn get(&self, idx: i32) -> bool {
+                (*self >> (idx as u32)) & 1 != 0
+            }
+            fn lowest_unset_bit(&self) -> i32 {
+                (!self).trailing_zeros() as i32
+            }
+            fn lowest_set_bit(&self) -> i32 {
+                self.trailing_zeros() as i32
+            }
+            fn next_set_bit(&self, idx: i32) -> i32 {
+                if idx >= $w {
+                    $w
+                } else {
+                    ((self >> (idx as u32)) << (idx as u32)).trailing_zeros() as i32
+                }
+            }
+            fn is_empty(&self) -> bool {
+                *self == 0
+            }
+        }
+
+        impl FixedBits for $t {
+            fn or(self, other: $t) -> $t {
+                self | other
+            }
+            fn and(self, other: $t) -> $t {
+                self & other
+            }
+            fn not(self) -> $t {
+                !self
+            }
+            fn shl(self, n: u32) -> $t {
+                self << n
+            }
+            fn shr(self, n: u32) -> $t {
+                self >> n
+            }
+        }
+    };
+}
+
+impl_bits_for_primitive!(u64, 64);
+impl_bits_for_primitive!(u128, 128);
+
+impl<const N: usize> Bits for [u64; N] {
     fn max_capacity() -> i32 {
-        64
+        (N * 64) as i32
     }
-    fn allocate(size: i32) -> u64 {
-        assert!(size <= 64);
-        0u64
+    fn is_fixed_width() -> bool {
+        true
+    }
+    fn allocate(size: i32) -> Result<[u64; N], PolymateError> {
+        if size as usize > N * 64 {
+            return Err(PolymateError::CapacityExceeded { cells: size, capacity: (N * 64) as i32 });
+        }
+        Ok([0u64; N])
     }
-    fn disjoint(&self, other: &u64) -> bool {
-        (*self & *other) == 0
+    fn disjoint(&self, other: &[u64; N]) -> bool {
+        for i in 0..N {
+            if (self[i] & other[i]) != 0 {
+                return false;
+            }
+        }
+        true
+    }
+    fn update(&mut self, other: &[u64; N]) {
+        for i in 0..N {
+            self[i] ^= other[i];
+        }
     }
-    fn update(&mut self, other: &u64) {
-        *self ^= *other;
+    fn clear(&mut self) {
+        *self = [0u64; N];
+    }
+    fn union_with(&mut self, other: &[u64; N]) {
+        for i in 0..N {
+            self[i] |= other[i];
+        }
+    }
+    fn subtract(&mut self, other: &[u64; N]) {
+        for i in 0..N {
+            self[i] &= !other[i];
+        }
+    }
+    fn count_ones(&self) -> i32 {
+        self.iter().map(|w| w.count_ones()).sum()
     }
     fn set(&mut self, idx: i32) {
-        *self |= 1u64 << (idx as u64);
+        self[(idx >> 6) as usize] |= 1u64 << ((idx & 63) as u64);
     }
     fn unset(&mut self, idx: i32) {
-        *self &= !(1u64 << (idx as u64));
+        self[(idx >> 6) as usize] &= !(1u64 << ((idx & 63) as u64));
+    }
+    fn get(&self, idx: i32) -> bool {
+        (self[(idx >> 6) as usize] >> ((idx & 63) as u64)) & 1 != 0
     }
     fn lowest_unset_bit(&self) -> i32 {
-        (!self).trailing_zeros() as i32
+        for (i, w) in self.iter().enumerate() {
+            let t = (!w).trailing_zeros();
+            if t != 64 {
+                return (t as i32) + ((i as i32) << 6);
+            }
+        }
+        (N * 64) as i32
     }
     fn lowest_set_bit(&self) -> i32 {
-        self.trailing_zeros() as i32
+        for (i, w) in self.iter().enumerate() {
+            let t = w.trailing_zeros();
+            if t != 64 {
+                return (t as i32) + ((i as i32) << 6);
+            }
+        }
+        (N * 64) as i32
+    }
+    fn next_set_bit(&self, idx: i32) -> i32 {
+        let mut i = (idx >> 6) as usize;
+        if i >= N {
+            return (N * 64) as i32;
+        }
+        let mut w = (self[i] >> ((idx & 63) as u64)) << ((idx & 63) as u64);
+        loop {
+            if w != 0 {
+                return (w.trailing_zeros() as i32) + ((i as i32) << 6);
+            }
+            i += 1;
+            if i == N {
+                return (N * 64) as i32;
+            }
+            w = self[i];
+        }
     }
     fn is_empty(&self) -> bool {
-        *self == 0
+        self.iter().all(|&w| w == 0)
+    }
+}
+
+impl<const N: usize> FixedBits for [u64; N] {
+    fn or(self, other: [u64; N]) -> [u64; N] {
+        let mut ret = self;
+        ret.union_with(&other);
+        ret
+    }
+    fn and(self, other: [u64; N]) -> [u64; N] {
+        let mut ret = self;
+        for i in 0..N {
+            ret[i] &= other[i];
+        }
+        ret
+    }
+    fn not(self) -> [u64; N] {
+        let mut ret = self;
+        for w in &mut ret {
+            *w = !*w;
+        }
+        ret
+    }
+    fn shl(self, n: u32) -> [u64; N] {
+        let words = (n >> 6) as usize;
+        let bits = n & 63;
+        let mut ret = [0u64; N];
+        for i in words..N {
+            ret[i] = self[i - words] << bits;
+            if bits > 0 && i > words {
+                ret[i] |= self[i - words - 1] >> (64 - bits);
+            }
+        }
+        ret
+    }
+    fn shr(self, n: u32) -> [u64; N] {
+        let words = (n >> 6) as usize;
+        let bits = n & 63;
+        let mut ret = [0u64; N];
+        for i in 0..N.saturating_sub(words) {
+            ret[i] = self[i + words] >> bits;
+            if bits > 0 && i + words + 1 < N {
+                ret[i] |= self[i + words + 1] << (64 - bits);
+            }
+        }
+        ret
     }
 }
 
@@ -49,13 +259,16 @@ pub struct Bitset {
 
 impl Bits for Bitset {
     fn max_capacity() -> i32 {
-        i32::max_value()
+        i32::MAX
+    }
+    fn is_fixed_width() -> bool {
+        false
     }
-    fn allocate(size: i32) -> Bitset {
-        Bitset {
+    fn allocate(size: i32) -> Result<Bitset, PolymateError> {
+        Ok(Bitset {
             data: vec![0u64; ((size + 63) >> 6) as usize],
             size,
-        }
+        })
     }
     fn disjoint(&self, other: &Bitset) -> bool {
         for i in 0..self.data.len() {
@@ -70,12 +283,37 @@ impl Bits for Bitset {
             self.data[i] ^= other.data[i];
         }
     }
+    fn clear(&mut self) {
+        for i in 0..self.data.len() {
+            self.data[i] = 0;
+        }
+    }
+    fn union_with(&mut self, other: &Bitset) {
+        for i in 0..self.data.len() {
+            self.data[i] |= other.data[i];
+        }
+    }
+    fn subtract(&mut self, other: &Bitset) {
+        for i in 0..self.data.len() {
+            self.data[i] &= !other.data[i];
+        }
+    }
+    fn count_ones(&self) -> i32 {
+        let mut ret = 0;
+        for i in 0..self.data.len() {
+            ret += self.data[i].count_ones() as i32;
+        }
+        ret
+    }
     fn set(&mut self, idx: i32) {
         self.data[(idx >> 6) as usize] |= 1u64 << ((idx & 63) as u64);
     }
     fn unset(&mut self, idx: i32) {
         self.data[(idx >> 6) as usize] &= !(1u64 << ((idx & 63) as u64));
     }
+    fn get(&self, idx: i32) -> bool {
+        (self.data[(idx >> 6) as usize] >> ((idx & 63) as u64)) & 1 != 0
+    }
     fn lowest_unset_bit(&self) -> i32 {
         for i in 0..self.data.len() {
             let t = (!self.data[i]).trailing_zeros();
@@ -94,6 +332,23 @@ impl Bits for Bitset {
         }
         self.size
     }
+    fn next_set_bit(&self, idx: i32) -> i32 {
+        let mut i = (idx >> 6) as usize;
+        if i >= self.data.len() {
+            return self.size;
+        }
+        let mut w = (self.data[i] >> ((idx & 63) as u64)) << ((idx & 63) as u64);
+        loop {
+            if w != 0 {
+                return (w.trailing_zeros() as i32) + ((i as i32) << 6);
+            }
+            i += 1;
+            if i == self.data.len() {
+                return self.size;
+            }
+            w = self.data[i];
+        }
+    }
     fn is_empty(&self) -> bool {
         for i in 0..self.data.len() {
             if self.data[i] != 0 {
@@ -103,3 +358,43 @@ impl Bits for Bitset {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_array_shifts() {
+        let mut a = [0u64; 3];
+        a.set(0);
+        a.set(63);
+        a.set(100);
+
+        let b = a.shl(70);
+        assert_eq!(b.count_ones(), 3);
+        assert!(b.get(70) && b.get(133) && b.get(170));
+
+        let c = a.shr(63);
+        assert_eq!(c.count_ones(), 2);
+        assert!(c.get(0) && c.get(37));
+
+        assert_eq!(a.shl(1).shr(1), a);
+        assert!(a.shl(192 - 1).shr(192 - 1).get(0));
+        assert_eq!(a.not().lowest_unset_bit(), 0);
+        assert_eq!(a.next_set_bit(64), 100);
+        assert_eq!(a.next_set_bit(101), 192);
+    }
+
+    #[test]
+    fn test_allocate() {
+        assert_eq!(u64::allocate(64), Ok(0));
+        assert_eq!(u64::allocate(65), Err(PolymateError::CapacityExceeded { cells: 65, capacity: 64 }));
+        assert_eq!(<[u64; 3]>::allocate(193), Err(PolymateError::CapacityExceeded { cells: 193, capacity: 192 }));
+        assert_eq!(Bitset::allocate(1000).unwrap().count_ones(), 0);
+
+        let shape = Shape::filled(Coord { x: 10, y: 10, z: 1 });
+        let piece = Shape::filled(Coord { x: 2, y: 1, z: 1 });
+        assert_eq!(shape.get_piece_mask::<u128>(&piece, Coord { x: 0, y: 0, z: 0 }), Ok(1 | 1 << 10));
+        assert!(shape.get_piece_mask::<u64>(&piece, Coord { x: 0, y: 0, z: 0 }).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use super::*;
+
+use std::ops::ControlFlow;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Callback receiving answers along with the index of the task they were found in
+/// and their orbit sizes (if `SolveOptions::count_symmetric` is set).
+pub type AnswerCallback<'a> = dyn Fn(usize, &Answer, Option<u64>) -> ControlFlow<()> + Sync + 'a;
+
+/// State shared by all threads working on the same search.
+pub struct SharedState {
+    stop: AtomicBool,
+    status: Mutex<SearchStatus>,
+    search_steps: AtomicU64,
+}
+
+impl SharedState {
+    pub fn new() -> SharedState {
+        SharedState {
+            stop: AtomicBool::new(false),
+            status: Mutex::new(SearchStatus::Completed),
+            search_steps: AtomicU64::new(0),
+        }
+    }
+
+    /// The status of the whole search, which is decided by the thread stopping the search first.
+    pub fn status(&self) -> SearchStatus {
+        *self.status.lock().unwrap()
+    }
+}
+
+/// Mutable state of the search owned by a single thread.
+pub struct SearchContext<'a> {
+    pub answers: Answers,
+    callback: &'a AnswerCallback<'a>,
+    options: &'a SolveOptions,
+    shared: &'a SharedState,
+    pub terminated: bool,
+
+    /// Symmetry used for deduplicating answers in the current task.
+    pub symmetry: Symmetry,
+    /// Index of the current task.
+    pub task: usize,
+
+    /// `answers.search_steps` already added to `shared.search_steps`.
+    flushed_steps: u64,
+    /// Search steps taken by the other threads as of the last synchronization.
+    others_steps: u64,
+}
+
+/// The deadline and the cancel flag are checked once per this number of search steps.
+pub(crate) const INTERRUPT_CHECK_INTERVAL: u64 = 1 << 12;
+
+impl<'a> SearchContext<'a> {
+    pub fn new(options: &'a SolveOptions, shared: &'a SharedState, callback: &'a AnswerCallback<'a>) -> SearchContext<'a> {
+        let mut answers = Answers::new();
+        if options.count_symmetric {
+            answers.raw_count = Some(0);
+        }
+        SearchContext {
+            answers,
+            callback,
+            options,
+            shared,
+            terminated: false,
+            symmetry: 1,
+            task: 0,
+            flushed_steps: 0,
+            others_steps: 0,
+        }
+    }
+
+    pub fn terminate(&mut self, status: SearchStatus) {
+        if !self.shared.stop.swap(true, Ordering::Relaxed) {
+            *self.shared.status.lock().unwrap() = status;
+        }
+        self.terminated = true;
+    }
+
+    /// Counts one search step.
+    /// Returns `true` if the search must be stopped instead of taking the step.
+    ///
+    /// The step limit is exact in a single-threaded search,
+    /// but can be slightly exceeded in a multithreaded one.
+    #[inline]
+    pub fn step(&mut self) -> bool {
+        if let Some(lim) = self.options.max_search_steps {
+            if self.others_steps + self.answers.search_steps >= lim {
+                self.terminate(SearchStatus::StepLimitReached);
+                return true;
+            }
+        }
+        self.answers.search_steps += 1;
+        if self.answers.search_steps.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+            return self.check_interrupt();
+        }
+        false
+    }
+
+    /// Publishes the search steps taken so far and checks all the interruption conditions.
+    pub fn check_interrupt(&mut self) -> bool {
+        let delta = self.answers.search_steps - self.flushed_steps;
+        let total = self.shared.search_steps.fetch_add(delta, Ordering::Relaxed) + delta;
+        self.flushed_steps = self.answers.search_steps;
+        self.others_steps = total - self.flushed_steps;
+
+        if self.shared.stop.load(Ordering::Relaxed) {
+            self.terminated = true;
+            return true;
+        }
+        if let Some(ref cancel) = self.options.cancel {
+            if cancel.load(Ordering::Relaxed) {
+                self.terminate(SearchStatus::Cancelled);
+                return true;
+            }
+        }
+        if let Some(deadline) = self.options.deadline {
+            if Instant::now() >= deadline {
+                self.terminate(SearchStatus::TimedOut);
+                return true;
+            }
+        }
+        if let Some(lim) = self.options.max_search_steps {
+            if total >= lim {
+                self.terminate(SearchStatus::StepLimitReached);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+
+/// Saves the answer given by `answer_raw` unless it is a duplicate of another one.
+pub fn save_answer<T: Bits>(dic: &Dictionary<T>, answer_raw: &Vec<(i32, i32)>, ctx: &mut SearchContext) {
+    // copies may be withheld from the search, so the pieces used are counted in the answer
+    let mut rem_piece = dic.piece_count.clone();
+    for &(piece, _) in answer_raw {
+        if piece >= 0 {
+            rem_piece[piece as usize] -= 1;
+        }
+    }
+
+    // check for uniqueness
+    let mut answer = Answer::from_answer(dic, answer_raw);
+    if !answer.is_canonical(dic, ctx.symmetry, &rem_piece) {
+        return;
+    }
+
+    // count the answers equivalent to this one under all the symmetries, not only the ones in `ctx.symmetry`
+    let orbit_size = if let Some(ref mut raw_count) = ctx.answers.raw_count {
+        let orbit_size = answer.orbit_size(dic, dic.target_symmetry, &rem_piece);
+        *raw_count += orbit_size;
+        Some(orbit_size)
+    } else {
+        None
+    };
+
+    // show the blocked cells and the fixed pieces, whose copies are numbered after the ones placed in the search
+    if let Some(ref preset) = dic.preset {
+        for cd in answer.size() {
+            let (piece, copy) = preset[cd];
+            if piece >= 0 {
+                answer[cd] = (piece, dic.piece_count[piece as usize] - rem_piece[piece as usize] + copy);
+            } else if piece == BLOCKED.0 {
+                answer[cd] = BLOCKED;
+            }
+        }
+        let total_piece_count = (0..rem_piece.len()).map(|i| dic.piece_count[i] + dic.preset_count[i]).collect::<Vec<_>>();
+        answer.reindex(&total_piece_count, &rem_piece);
+    }
+
+    // save answer
+    ctx.answers.count += 1;
+
+    if (ctx.callback)(ctx.task, &answer, orbit_size).is_break() {
+        ctx.terminate(SearchStatus::Stopped);
+    }
+}
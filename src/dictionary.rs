@@ -2,42 +2,72 @@ use super::*;
 
 use std::cmp::Ordering;
 
+/// The special pieces have at most this many copies, since all the combinations of their placements are enumerated.
+const MAX_SPECIAL_PIECE_COUNT: i32 = 2;
+
 pub struct Dictionary<T: Bits> {
     pub n_target_cells: i32,
+    /// Number of copies of each piece left for the search.
     pub piece_count: Vec<i32>,
     pub placements: Vec<Vec<Vec<T>>>, // cell, piece, orientation
+    /// The cells left for the search.
     pub target: Shape,
     pub target_symmetry: Symmetry,
+    /// The blocked cells (`BLOCKED`) and the fixed placements of the puzzle, if any.
+    pub preset: Option<Answer>,
+    /// Number of fixed copies of each piece.
+    pub preset_count: Vec<i32>,
     pub id_to_coord: Vec<Coord>,
+    coord_to_id: Vec<i32>,
+    empty_mask: T,
 
     // about the special piece for uniqueneess
+    /// Number of copies of each piece left for the search from each initial placement,
+    /// excluding the copies of the special pieces withheld from it.
     pub initial_piece_count: Vec<Vec<i32>>,
     pub initial_placement: Vec<T>,
     pub initial_placement_id: Vec<Vec<(i32, i32, i32)>>, // cell, piece, orientation
     pub initial_symmetry: Vec<Symmetry>,
 
-    pub mirror_pair: Vec<i32>,
+    /// For each transformation in `TRANSFORMATIONS`, the piece which each piece turns into, or `-1` if there is none.
+    pub piece_map: Vec<Vec<i32>>,
 
     pub isolated_cell_pruning: bool,
-    pub isolated_cell_pruning_x_ofs: u64,
-    pub isolated_cell_pruning_x_mask_lo: u64,
-    pub isolated_cell_pruning_x_mask_hi: u64,
-    pub isolated_cell_pruning_y_ofs: u64,
-    pub isolated_cell_pruning_y_mask_lo: u64,
-    pub isolated_cell_pruning_y_mask_hi: u64,
-    pub isolated_cell_pruning_z_ofs: u64,
-    pub isolated_cell_pruning_z_mask_lo: u64,
-    pub isolated_cell_pruning_z_mask_hi: u64,
-    
+    pub isolated_cell_pruning_x_ofs: u32,
+    pub isolated_cell_pruning_x_mask_lo: T,
+    pub isolated_cell_pruning_x_mask_hi: T,
+    pub isolated_cell_pruning_y_ofs: u32,
+    pub isolated_cell_pruning_y_mask_lo: T,
+    pub isolated_cell_pruning_y_mask_hi: T,
+    pub isolated_cell_pruning_z_ofs: u32,
+    pub isolated_cell_pruning_z_mask_lo: T,
+    pub isolated_cell_pruning_z_mask_hi: T,
+
+    // about the dead region pruning
+    pub region_pruning: bool,
+    pub region_pruning_limit: i32,
+    pub neighbor_mask: Vec<T>,
+    pub piece_volume: Vec<i32>,
 }
 
 impl<T: Bits> Dictionary<T> {
-    pub fn new(problem: &Puzzle) -> Dictionary<T> {
-        let n_pieces = problem.pieces.len();
+    pub fn new(problem: &Puzzle) -> Result<Dictionary<T>, PolymateError> {
+        Dictionary::with_objective(problem, Objective::ExactCover)
+    }
 
-        let piece_count = problem.pieces.iter().map(|&(_, c)| c).collect::<Vec<i32>>();
+    /// Same as `new`, but the pieces may have fewer cells than the target unless `objective` is `Objective::ExactCover`.
+    pub fn with_objective(problem: &Puzzle, objective: Objective) -> Result<Dictionary<T>, PolymateError> {
+        if problem.orientations.len() != problem.pieces.len() {
+            return Err(PolymateError::OrientationsMismatch { pieces: problem.pieces.len(), orientations: problem.orientations.len() });
+        }
+        if problem.piece_names.len() != problem.pieces.len() {
+            return Err(PolymateError::PieceNamesMismatch { pieces: problem.pieces.len(), names: problem.piece_names.len() });
+        }
+        let n_pieces = problem.pieces.len();
 
-        let target = &problem.target;
+        let Constrained { target, piece_count, preset, preset_count } = apply_constraints(problem)?;
+        validate(problem, &target, &piece_count, objective == Objective::ExactCover)?;
+        let target = &target;
         let target_size = target.size();
         let mut target_symmetry = target.symmetry();
 
@@ -56,68 +86,52 @@ impl<T: Bits> Dictionary<T> {
             }
         }
 
-        if n_target_cells > T::max_capacity() { panic!("Too small capacity"); }
+        let empty_mask = T::allocate(n_target_cells)?;
 
-        let mut piece_canonical = vec![];
+        // the shapes each piece can be placed as, which decide how the pieces are mapped by the symmetries of the target
+        let mut piece_shapes = vec![];
         for i in 0..n_pieces {
-            let p = problem.pieces[i].0.canonize();
-            let mp = problem.pieces[i].0.trans(Transformation::id().flip_x()).canonize();
-
-            piece_canonical.push((p, mp));
+            piece_shapes.push(problem.pieces[i].0.orientations_under(&problem.orientations[i].transformations()));
         }
-        let mut mirror_pair = vec![];
-        for i in 0..n_pieces {
-            let mut pair = -1;
-            for j in 0..n_pieces {
-                if piece_canonical[i].1 == piece_canonical[j].0 {
-                    pair = j as i32;
-                    break;
+        let piece_map = TRANSFORMATIONS.iter().map(|&t| compute_piece_map(&piece_shapes, t)).collect::<Vec<_>>();
+
+        // answers are equivalent only under symmetries keeping the blocked cells and the fixed pieces
+        if let Some(ref preset) = preset {
+            for s in 1..48 {
+                if (target_symmetry & (1u64 << s)) == 0 { continue; }
+                let mut moved = preset.trans(TRANSFORMATIONS[s]);
+                moved.map_pieces(&piece_map[s]);
+                if target_size.into_iter().any(|cd| moved[cd].0 != preset[cd].0) {
+                    target_symmetry &= !(1u64 << s);
                 }
             }
-            mirror_pair.push(pair);
         }
 
-        let mut id_to_coord = vec![];
-        for cd in target_size {
-            if target.get(cd) {
-                id_to_coord.push(cd);
-            }
+        let id_to_coord = compute_cell_order(target);
+        let mut coord_to_id = vec![-1; (target_size.x * target_size.y * target_size.z) as usize];
+        for i in 0..id_to_coord.len() {
+            coord_to_id[shape_index(target_size, id_to_coord[i])] = i as i32;
         }
-        
-        let mut placements = vec![vec![vec![]; n_pieces as usize]; n_target_cells as usize];
+
+        let mut placements = vec![vec![vec![]; n_pieces]; n_target_cells as usize];
         
         for i in 0..n_pieces {
-            let piece = &problem.pieces[i].0;
-
-            // compute unique rotation patterns
-            let mut rots = vec![];
-            for &rot in &ROTATIONS {
-                let piece_rot = piece.trans(rot);
-
-                // is unique?
-                let mut is_unique = true;
-
-                for p in &rots {
-                    if *p == piece_rot {
-                        is_unique = false;
-                        break;
-                    }
-                }
-
-                if is_unique {
-                    rots.push(piece_rot);
-                }
-            }
+            let rots = &piece_shapes[i];
 
             // compute all possible placements
-            for p in &rots {
+            for p in rots {
                 let p_size = p.size();
 
                 if p_size.x > target_size.x || p_size.y > target_size.y || p_size.z > target_size.z { continue; }
 
                 for offset in (target_size - p_size + Coord { x: 1, y: 1, z: 1 }) {
                     if target.is_fit(p, offset) {
-                        let mask = target.get_piece_mask::<T>(p, offset);
+                        let mut mask = empty_mask.clone();
+                        for cd in p_size {
+                            if p.get(cd) {
+                                mask.set(coord_to_id[shape_index(target_size, cd + offset)]);
+                            }
+                        }
                         let handle = mask.lowest_set_bit();
 
                         placements[handle as usize][i].push(mask);
@@ -126,27 +140,40 @@ impl<T: Bits> Dictionary<T> {
             }
         }
 
-        // handle the special piece
+        // handle the special pieces
         let mut total_piece_volume = 0;
-        for i in 0..n_pieces {
-            total_piece_volume += problem.pieces[i].0.volume() * problem.pieces[i].1;
+        for (piece, &count) in problem.pieces.iter().zip(&piece_count) {
+            total_piece_volume += piece.0.volume() * count;
         }
         let use_all_pieces = total_piece_volume == target.volume();
 
+        // the symmetries mapping each piece to one with the same count apply to all answers
+        let mut global_symmetry = target_symmetry;
+        for (s, map) in piece_map.iter().enumerate().skip(1) {
+            if (0..n_pieces).any(|i| piece_count[i] > 0 && (map[i] < 0 || piece_count[map[i] as usize] != piece_count[i])) {
+                global_symmetry &= !(1u64 << s);
+            }
+        }
+
         let mut special_piece_cand = vec![];
-        
-        if use_all_pieces {
+        let mut initial_target_symmetry = target_symmetry;
+
+        // With spare pieces, the other symmetries may still apply to answers not using all the copies of some piece,
+        // so the special pieces are used only if there are no such symmetries.
+        if use_all_pieces || global_symmetry == target_symmetry {
+            initial_target_symmetry = global_symmetry;
             for i in 0..n_pieces {
-                if mirror_pair[i] != i as i32 { continue; }
-                if problem.pieces[i].1 > 1 { continue; }
+                if (1..48).any(|s| (initial_target_symmetry & (1u64 << s)) != 0 && piece_map[s][i] != i as i32) { continue; }
+                if piece_count[i] == 0 || piece_count[i] > MAX_SPECIAL_PIECE_COUNT { continue; }
 
                 special_piece_cand.push(i);
-                continue;
             }
         }
-        while special_piece_cand.len() > 2 {
-            special_piece_cand.pop();
-        }
+        // prefer pieces with fewer copies and then with fewer placements, which yield fewer initial placements,
+        // and use only one piece with multiple copies, as the combinations of the placements of two are too many
+        special_piece_cand.sort_by_key(|&i| (piece_count[i], placements.iter().map(|p| p[i].len()).sum::<usize>()));
+        let n_special_pieces = if !special_piece_cand.is_empty() && piece_count[special_piece_cand[0]] > 1 { 1 } else { 2 };
+        special_piece_cand.truncate(n_special_pieces);
 
         let mut initial_piece_count = vec![];
         let mut initial_placement = vec![];
@@ -156,13 +183,14 @@ impl<T: Bits> Dictionary<T> {
         Dictionary::<T>::compute_initial_placement(
             0,
             &special_piece_cand,
+            use_all_pieces,
             &placements,
             &id_to_coord,
-            &target,
+            target,
             &mut piece_count.clone(),
-            &mut T::allocate(n_target_cells),
+            &mut empty_mask.clone(),
             &mut vec![],
-            target_symmetry,
+            initial_target_symmetry,
             &mut initial_piece_count,
             &mut initial_placement,
             &mut initial_placement_id,
@@ -170,62 +198,101 @@ impl<T: Bits> Dictionary<T> {
         );
 
         let mut isolated_cell_pruning = true;
-        let mut isolated_cell_pruning_x_ofs = 0u64;
-        let mut isolated_cell_pruning_x_mask_lo = 0u64;
-        let mut isolated_cell_pruning_x_mask_hi = 0u64;
-        let mut isolated_cell_pruning_y_ofs = 0u64;
-        let mut isolated_cell_pruning_y_mask_lo = 0u64;
-        let mut isolated_cell_pruning_y_mask_hi = 0u64;
-        let mut isolated_cell_pruning_z_ofs = 0u64;
-        let mut isolated_cell_pruning_z_mask_lo = 0u64;
-        let mut isolated_cell_pruning_z_mask_hi = 0u64;
+        let mut isolated_cell_pruning_x_ofs = 0u32;
+        let mut isolated_cell_pruning_x_mask_lo = empty_mask.clone();
+        let mut isolated_cell_pruning_x_mask_hi = empty_mask.clone();
+        let mut isolated_cell_pruning_y_ofs = 0u32;
+        let mut isolated_cell_pruning_y_mask_lo = empty_mask.clone();
+        let mut isolated_cell_pruning_y_mask_hi = empty_mask.clone();
+        let mut isolated_cell_pruning_z_ofs = 0u32;
+        let mut isolated_cell_pruning_z_mask_lo = empty_mask.clone();
+        let mut isolated_cell_pruning_z_mask_hi = empty_mask.clone();
         
         for i in 0..n_pieces {
             if problem.pieces[i].0.volume() == 1 { isolated_cell_pruning = false; }
         }
         if target.volume() != (target_size.x * target_size.y * target_size.z) { isolated_cell_pruning = false; }
+        if !T::is_fixed_width() { isolated_cell_pruning = false; }
 
         if isolated_cell_pruning {
-            isolated_cell_pruning_x_ofs = (target_size.y * target_size.z) as u64;
-            isolated_cell_pruning_y_ofs = target_size.z as u64;
-            isolated_cell_pruning_z_ofs = 1;
+            // cells of a box are numbered in lexicographic order of some permutation of the axes,
+            // so moving along an axis always shifts the cell id by the same amount
+            let origin = coord_to_id[0];
+            let axis_ofs = |d: Coord| {
+                if d.x < target_size.x && d.y < target_size.y && d.z < target_size.z {
+                    (coord_to_id[shape_index(target_size, d)] - origin) as u32
+                } else {
+                    0
+                }
+            };
+            isolated_cell_pruning_x_ofs = axis_ofs(Coord { x: 1, y: 0, z: 0 });
+            isolated_cell_pruning_y_ofs = axis_ofs(Coord { x: 0, y: 1, z: 0 });
+            isolated_cell_pruning_z_ofs = axis_ofs(Coord { x: 0, y: 0, z: 1 });
             for cd in target_size {
-                let idx = (cd.x * target_size.y * target_size.z + cd.y * target_size.z + cd.z) as u64;
+                let idx = coord_to_id[shape_index(target_size, cd)];
                 if cd.x == 0 {
-                    isolated_cell_pruning_x_mask_lo |= 1u64 << idx;
+                    isolated_cell_pruning_x_mask_lo.set(idx);
                 }
                 if cd.x == target_size.x - 1 {
-                    isolated_cell_pruning_x_mask_hi |= 1u64 << idx;
+                    isolated_cell_pruning_x_mask_hi.set(idx);
                 }
                 if cd.y == 0 {
-                    isolated_cell_pruning_y_mask_lo |= 1u64 << idx;
+                    isolated_cell_pruning_y_mask_lo.set(idx);
                 }
                 if cd.y == target_size.y - 1 {
-                    isolated_cell_pruning_y_mask_hi |= 1u64 << idx;
+                    isolated_cell_pruning_y_mask_hi.set(idx);
                 }
                 if cd.z == 0 {
-                    isolated_cell_pruning_z_mask_lo |= 1u64 << idx;
+                    isolated_cell_pruning_z_mask_lo.set(idx);
                 }
                 if cd.z == target_size.z - 1 {
-                    isolated_cell_pruning_z_mask_hi |= 1u64 << idx;
+                    isolated_cell_pruning_z_mask_hi.set(idx);
+                }
+            }
+        }
+
+        let piece_volume = problem.pieces.iter().map(|(p, _)| p.volume()).collect::<Vec<i32>>();
+        let mut neighbor_mask = vec![empty_mask.clone(); n_target_cells as usize];
+        for i in 0..id_to_coord.len() {
+            let cd = id_to_coord[i];
+            for &d in &[
+                Coord { x: -1, y: 0, z: 0 }, Coord { x: 1, y: 0, z: 0 },
+                Coord { x: 0, y: -1, z: 0 }, Coord { x: 0, y: 1, z: 0 },
+                Coord { x: 0, y: 0, z: -1 }, Coord { x: 0, y: 0, z: 1 },
+            ] {
+                let nb = cd + d;
+                if 0 <= nb.x && nb.x < target_size.x && 0 <= nb.y && nb.y < target_size.y && 0 <= nb.z && nb.z < target_size.z {
+                    let id = coord_to_id[shape_index(target_size, nb)];
+                    if id >= 0 {
+                        neighbor_mask[i].set(id);
+                    }
                 }
             }
         }
 
-        Dictionary {
+        // Regions of empty cells next to each placed piece are examined unless the cheaper isolated-cell check is available.
+        // Only regions smaller than the largest piece are examined, as larger ones are costly to examine and rarely dead.
+        let max_volume = piece_volume.iter().cloned().max().unwrap_or(0);
+        let region_pruning_limit = std::cmp::min(63, max_volume - 1);
+        let region_pruning = !isolated_cell_pruning && region_pruning_limit >= 1;
+
+        Ok(Dictionary {
             n_target_cells,
             piece_count,
             placements,
             target: target.clone(),
             target_symmetry,
+            preset,
+            preset_count,
             id_to_coord,
+            coord_to_id,
 
             initial_piece_count,
             initial_placement,
             initial_placement_id,
             initial_symmetry,
 
-            mirror_pair,
+            piece_map,
 
             isolated_cell_pruning,
             isolated_cell_pruning_x_ofs,
@@ -237,12 +304,41 @@ impl<T: Bits> Dictionary<T> {
             isolated_cell_pruning_z_ofs,
             isolated_cell_pruning_z_mask_lo,
             isolated_cell_pruning_z_mask_hi,
+
+            region_pruning,
+            region_pruning_limit,
+            neighbor_mask,
+            piece_volume,
+            empty_mask,
+        })
+    }
+
+    /// Returns a mask with no cells set.
+    pub fn empty_mask(&self) -> T {
+        self.empty_mask.clone()
+    }
+
+    /// Returns the id of the target cell at `cd`, or `-1` if `cd` is not in the target.
+    pub fn cell_id(&self, cd: Coord) -> i32 {
+        let size = self.target.size();
+        if 0 <= cd.x && cd.x < size.x && 0 <= cd.y && cd.y < size.y && 0 <= cd.z && cd.z < size.z {
+            self.coord_to_id[shape_index(size, cd)]
+        } else {
+            -1
         }
     }
 
+    /// Enumerates the initial placements of the special pieces from `special_piece_cand[idx]` on.
+    ///
+    /// For each special piece, some number of its copies (all of them if `use_all_pieces`) are placed,
+    /// and the copies not placed are withheld from the search, so each answer is reached from the initial placement
+    /// consisting of its special pieces. Only the placements leaving the target not greater than its transformations
+    /// under `current_symmetry` are kept, and the transformations leaving it the same remain for `Answer::is_canonical`.
+    #[allow(clippy::too_many_arguments)]
     fn compute_initial_placement(
         idx: usize,
         special_piece_cand: &Vec<usize>,
+        use_all_pieces: bool,
         placements: &Vec<Vec<Vec<T>>>,
         id_to_coord: &Vec<Coord>,
         current_target: &Shape,
@@ -264,63 +360,336 @@ impl<T: Bits> Dictionary<T> {
         }
 
         let p = special_piece_cand[idx];
-        current_piece_count[p] -= 1;
+        let count = current_piece_count[p];
+        current_piece_count[p] = 0;
+
+        for n_copies in (if use_all_pieces { count } else { 0 })..=count {
+            Dictionary::place_special_piece(
+                idx,
+                n_copies,
+                0,
+                special_piece_cand,
+                use_all_pieces,
+                placements,
+                id_to_coord,
+                current_target,
+                current_piece_count,
+                current_placement,
+                current_placement_id,
+                current_symmetry,
+                initial_piece_count,
+                initial_placement,
+                initial_placement_id,
+                initial_symmetry
+            );
+        }
 
-        for i in 0..placements.len() {
+        current_piece_count[p] = count;
+    }
+
+    /// Places `n_copies` more copies of `special_piece_cand[idx]` in cells from `start` on, in the increasing order of cells,
+    /// and then continues with the next special piece.
+    #[allow(clippy::too_many_arguments)]
+    fn place_special_piece(
+        idx: usize,
+        n_copies: i32,
+        start: usize,
+        special_piece_cand: &Vec<usize>,
+        use_all_pieces: bool,
+        placements: &Vec<Vec<Vec<T>>>,
+        id_to_coord: &Vec<Coord>,
+        current_target: &Shape,
+        current_piece_count: &mut Vec<i32>,
+        current_placement: &mut T,
+        current_placement_id: &mut Vec<(i32, i32, i32)>,
+        current_symmetry: Symmetry,
+        initial_piece_count: &mut Vec<Vec<i32>>,
+        initial_placement: &mut Vec<T>,
+        initial_placement_id: &mut Vec<Vec<(i32, i32, i32)>>,
+        initial_symmetry: &mut Vec<Symmetry>,
+    ) {
+        if n_copies == 0 {
+            let mut new_symmetry = 1u64;
+            for (s, &t) in TRANSFORMATIONS.iter().enumerate().skip(1) {
+                if (current_symmetry & (1u64 << s)) != 0 {
+                    let rot_field = current_target.trans(t);
+                    match current_target.cmp(&rot_field) {
+                        Ordering::Less => (),
+                        Ordering::Equal => new_symmetry |= 1u64 << s,
+                        Ordering::Greater => return,
+                    }
+                }
+            }
+
+            Dictionary::compute_initial_placement(
+                idx + 1,
+                special_piece_cand,
+                use_all_pieces,
+                placements,
+                id_to_coord,
+                current_target,
+                current_piece_count,
+                current_placement,
+                current_placement_id,
+                new_symmetry,
+                initial_piece_count,
+                initial_placement,
+                initial_placement_id,
+                initial_symmetry
+            );
+            return;
+        }
+
+        let p = special_piece_cand[idx];
+        for i in start..placements.len() {
             for j in 0..placements[i][p].len() {
+                if !current_placement.disjoint(&placements[i][p][j]) { continue; }
+
                 let mut new_target = current_target.clone();
                 let mut pl = placements[i][p][j].clone();
-
-                if !current_placement.disjoint(&pl) { continue; }
-
                 while !pl.is_empty() {
                     let id = pl.lowest_set_bit();
                     pl.unset(id);
                     new_target.set(id_to_coord[id as usize], false);
                 }
 
-                let mut new_symmetry = 1u64;
-                let mut isok = true;
-                for s in 1..48 {
-                    if (current_symmetry & (1u64 << s)) != 0 {
-                        let rot_field = new_target.trans(TRANSFORMATIONS[s]);
-                        match new_target.cmp(&rot_field) {
-                            Ordering::Less => (),
-                            Ordering::Equal => new_symmetry |= 1u64 << s,
-                            Ordering::Greater => {
-                                isok = false;
-                                break;
-                            },
-                        }
-                    }
+                current_placement.update(&placements[i][p][j]);
+                current_placement_id.push((i as i32, p as i32, j as i32));
+                Dictionary::place_special_piece(
+                    idx,
+                    n_copies - 1,
+                    i + 1,
+                    special_piece_cand,
+                    use_all_pieces,
+                    placements,
+                    id_to_coord,
+                    &new_target,
+                    current_piece_count,
+                    current_placement,
+                    current_placement_id,
+                    current_symmetry,
+                    initial_piece_count,
+                    initial_placement,
+                    initial_placement_id,
+                    initial_symmetry
+                );
+                current_placement_id.pop();
+                current_placement.update(&placements[i][p][j]);
+            }
+        }
+    }
+}
+
+/// Maps each piece to the piece whose placeable shapes are those of the piece transformed by `trans`.
+/// A piece is mapped to itself if possible, and no two pieces are mapped to the same one.
+fn compute_piece_map(piece_shapes: &[Vec<Shape>], trans: Transformation) -> Vec<i32> {
+    let n_pieces = piece_shapes.len();
+    let sorted = |shapes: Vec<Shape>| {
+        let mut shapes = shapes;
+        shapes.sort();
+        shapes
+    };
+    let piece_shapes = piece_shapes.iter().map(|shapes| sorted(shapes.clone())).collect::<Vec<_>>();
+    let mut ret = vec![-1; n_pieces];
+    let mut taken = vec![false; n_pieces];
+    let mut images = vec![];
+    for i in 0..n_pieces {
+        let image = sorted(piece_shapes[i].iter().map(|p| p.trans(trans)).collect());
+        if image == piece_shapes[i] {
+            ret[i] = i as i32;
+            taken[i] = true;
+        }
+        images.push(image);
+    }
+    for i in 0..n_pieces {
+        if ret[i] >= 0 { continue; }
+        for j in 0..n_pieces {
+            if !taken[j] && images[i] == piece_shapes[j] {
+                ret[i] = j as i32;
+                taken[j] = true;
+                break;
+            }
+        }
+    }
+    ret
+}
+
+/// What is left of a puzzle after removing the blocked cells and the fixed pieces.
+struct Constrained {
+    target: Shape,
+    piece_count: Vec<i32>,
+    preset: Option<Answer>,
+    preset_count: Vec<i32>,
+}
+
+/// Removes the blocked cells and the fixed pieces from the target and the pieces of `problem`.
+fn apply_constraints(problem: &Puzzle) -> Result<Constrained, PolymateError> {
+    let mut target = problem.target.clone();
+    let size = target.size();
+    let in_target = |target: &Shape, cd: Coord| {
+        0 <= cd.x && cd.x < size.x && 0 <= cd.y && cd.y < size.y && 0 <= cd.z && cd.z < size.z && target.get(cd)
+    };
+    let mut piece_count = problem.pieces.iter().map(|&(_, c)| c).collect::<Vec<i32>>();
+    let mut preset = Answer::new(size);
+    let mut preset_count = vec![0; problem.pieces.len()];
+
+    for i in 0..problem.fixed.len() {
+        let fixed = &problem.fixed[i];
+        if fixed.piece >= problem.pieces.len() || piece_count[fixed.piece] == 0 {
+            return Err(PolymateError::InvalidFixedPlacement(i));
+        }
+        let shape = problem.pieces[fixed.piece].0.trans(fixed.transformation);
+        for cd in shape.size() {
+            if shape.get(cd) {
+                let cd = cd + fixed.offset;
+                if !in_target(&target, cd) {
+                    return Err(PolymateError::InvalidFixedPlacement(i));
                 }
+                target.set(cd, false);
+                preset[cd] = (fixed.piece as i32, preset_count[fixed.piece]);
+            }
+        }
+        piece_count[fixed.piece] -= 1;
+        preset_count[fixed.piece] += 1;
+    }
+    for &cd in &problem.blocked {
+        let is_blocked_twice = in_target(&problem.target, cd) && preset[cd] == BLOCKED;
+        if !in_target(&target, cd) && !is_blocked_twice {
+            return Err(PolymateError::InvalidBlockedCell(cd));
+        }
+        target.set(cd, false);
+        preset[cd] = BLOCKED;
+    }
+    let preset = if problem.fixed.is_empty() && problem.blocked.is_empty() { None } else { Some(preset) };
+    Ok(Constrained { target, piece_count, preset, preset_count })
+}
+
+/// Checks that `problem` is well-formed regardless of the bit set type,
+/// where `target` and `piece_count` are what is left by the constraints.
+/// The pieces must fill the target if `exact` is `true`.
+fn validate(problem: &Puzzle, target: &Shape, piece_count: &[i32], exact: bool) -> Result<(), PolymateError> {
+    if problem.target.volume() == 0 {
+        return Err(PolymateError::EmptyTarget);
+    }
+
+    let target_volume = target.volume();
+    let mut pieces_volume = 0;
+    for (i, (&(ref piece, original_count), &count)) in problem.pieces.iter().zip(piece_count).enumerate() {
+        if original_count < 0 {
+            return Err(PolymateError::NegativePieceCount(i));
+        }
+        let volume = piece.volume();
+        if volume == 0 {
+            return Err(PolymateError::ZeroVolumePiece(i));
+        }
+        if count > 0 && volume > problem.target.volume() {
+            return Err(PolymateError::PieceLargerThanTarget(i));
+        }
+        pieces_volume += volume * count;
+    }
+    if exact && pieces_volume < target_volume {
+        return Err(PolymateError::VolumeMismatch { pieces: pieces_volume, target: target_volume });
+    }
+    Ok(())
+}
+
+fn shape_index(size: Coord, cd: Coord) -> usize {
+    ((cd.x * size.y + cd.y) * size.z + cd.z) as usize
+}
+
+/// Decides the numbering of the cells of `target`.
+///
+/// The search fills the target roughly in the order of cell ids, so the frontier between
+/// filled and empty cells is kept narrow by an order with a small bandwidth
+/// (the largest difference of the ids of adjacent cells).
+/// The candidates are the lexicographic orders for each permutation of the axes and,
+/// unless `target` is a box, the Cuthill-McKee orders starting from the first cell of each of them.
+/// Ties are broken in favor of earlier candidates, so the natural order (x-major) is kept when it is the best.
+fn compute_cell_order(target: &Shape) -> Vec<Coord> {
+    let size = target.size();
+    let cells = size.into_iter().filter(|&cd| target.get(cd)).collect::<Vec<_>>();
+    let is_box = cells.len() as i32 == size.x * size.y * size.z;
+
+    let neighbors = |cd: Coord| {
+        let mut ret = vec![];
+        for &d in &[
+            Coord { x: -1, y: 0, z: 0 }, Coord { x: 1, y: 0, z: 0 },
+            Coord { x: 0, y: -1, z: 0 }, Coord { x: 0, y: 1, z: 0 },
+            Coord { x: 0, y: 0, z: -1 }, Coord { x: 0, y: 0, z: 1 },
+        ] {
+            let nb = cd + d;
+            if 0 <= nb.x && nb.x < size.x && 0 <= nb.y && nb.y < size.y && 0 <= nb.z && nb.z < size.z && target.get(nb) {
+                ret.push(nb);
+            }
+        }
+        ret
+    };
+    let bandwidth = |order: &Vec<Coord>| {
+        let mut id = vec![0; (size.x * size.y * size.z) as usize];
+        for i in 0..order.len() {
+            id[shape_index(size, order[i])] = i as i32;
+        }
+        let mut ret = 0;
+        for &cd in order {
+            for nb in neighbors(cd) {
+                ret = std::cmp::max(ret, id[shape_index(size, nb)] - id[shape_index(size, cd)]);
+            }
+        }
+        ret
+    };
+
+    let mut candidates = vec![];
+    for &axes in &[[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]] {
+        let mut order = cells.clone();
+        order.sort_by_key(|cd| {
+            let v = [cd.x, cd.y, cd.z];
+            (v[axes[0]], v[axes[1]], v[axes[2]])
+        });
+        candidates.push(order);
+    }
+    if !is_box {
+        for i in 0..6 {
+            if cells.is_empty() { break; }
+            let start = candidates[i][0];
+            let mut key = vec![0; (size.x * size.y * size.z) as usize];
+            for j in 0..candidates[i].len() {
+                key[shape_index(size, candidates[i][j])] = j;
+            }
 
-                if isok {
-                    current_placement.update(&placements[i][p][j]);
-
-                    current_placement_id.push((i as i32, p as i32, j as i32));
-                    Dictionary::compute_initial_placement(
-                        idx + 1,
-                        special_piece_cand,
-                        placements,
-                        id_to_coord,
-                        &new_target,
-                        current_piece_count,
-                        current_placement,
-                        current_placement_id,
-                        new_symmetry,
-                        initial_piece_count,
-                        initial_placement,
-                        initial_placement_id,
-                        initial_symmetry
-                    );
-                    current_placement_id.pop();
-
-                    current_placement.update(&placements[i][p][j]);
+            // breadth-first search visiting neighbors with fewer neighbors first
+            let mut visited = vec![false; (size.x * size.y * size.z) as usize];
+            let mut order = vec![start];
+            visited[shape_index(size, start)] = true;
+            let mut head = 0;
+            while order.len() < cells.len() {
+                if head == order.len() {
+                    // disconnected target: continue from the first unvisited cell
+                    let next = candidates[i].iter().find(|&&cd| !visited[shape_index(size, cd)]).unwrap();
+                    visited[shape_index(size, *next)] = true;
+                    order.push(*next);
                 }
+                let mut nbs = neighbors(order[head]).into_iter()
+                    .filter(|&nb| !visited[shape_index(size, nb)])
+                    .collect::<Vec<_>>();
+                nbs.sort_by_key(|&nb| (neighbors(nb).len(), key[shape_index(size, nb)]));
+                for nb in nbs {
+                    visited[shape_index(size, nb)] = true;
+                    order.push(nb);
+                }
+                head += 1;
             }
+            candidates.push(order);
         }
+    }
 
-        current_piece_count[p] += 1;
+    let mut best = 0;
+    let mut best_bandwidth = bandwidth(&candidates[0]);
+    for (i, cand) in candidates.iter().enumerate().skip(1) {
+        let b = bandwidth(cand);
+        if b < best_bandwidth {
+            best = i;
+            best_bandwidth = b;
+        }
     }
+    candidates.swap_remove(best)
 }
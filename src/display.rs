@@ -0,0 +1,206 @@
+//! Drawing of shapes and answers as text.
+//!
+//! Cells are drawn as boxes whose borders appear only between different regions (piece instances of an answer,
+//! or the filled and empty cells of a shape), using Unicode box-drawing characters or, with the alternate flag
+//! (`{:#}`), ASCII `+`, `-` and `|`. The layers of a 3D shape or answer are drawn in the order of z,
+//! separated by blank lines.
+
+use super::*;
+
+use std::fmt;
+
+/// Draws the layers of a grid of `size`, where `region(cd)` tells which region the cell `cd` belongs to
+/// (`None` for cells outside all regions) and `label(cd)` is the text in the cell, at most `width` characters.
+fn write_grid<R, L>(f: &mut fmt::Formatter, size: Coord, width: usize, region: R, label: L) -> fmt::Result
+    where R: Fn(Coord) -> Option<(i32, i32)>, L: Fn(Coord) -> String
+{
+    let ascii = f.alternate();
+    let region_at = |x: i32, y: i32, z: i32| {
+        if 0 <= x && x < size.x && 0 <= y && y < size.y { region(Coord { x, y, z }) } else { None }
+    };
+
+    for z in 0..size.z {
+        if z > 0 {
+            writeln!(f)?;
+        }
+        for y in 0..(size.y + 1) {
+            // the border above row `y`
+            let mut line = String::new();
+            for x in 0..(size.x + 1) {
+                let up = region_at(x - 1, y - 1, z) != region_at(x, y - 1, z);
+                let down = region_at(x - 1, y, z) != region_at(x, y, z);
+                let left = region_at(x - 1, y - 1, z) != region_at(x - 1, y, z);
+                let right = region_at(x, y - 1, z) != region_at(x, y, z);
+                line.push(corner(up, down, left, right, ascii));
+                if x < size.x {
+                    let c = if right { if ascii { '-' } else { '─' } } else { ' ' };
+                    line.extend(std::iter::repeat_n(c, width));
+                }
+            }
+            writeln!(f, "{}", line.trim_end())?;
+
+            if y == size.y {
+                break;
+            }
+
+            // the cells of row `y` and the borders between them
+            let mut line = String::new();
+            for x in 0..(size.x + 1) {
+                let c = if region_at(x - 1, y, z) != region_at(x, y, z) { if ascii { '|' } else { '│' } } else { ' ' };
+                line.push(c);
+                if x < size.x {
+                    let text = label(Coord { x, y, z });
+                    let n = text.chars().count();
+                    let pad = width.saturating_sub(n);
+                    line.extend(std::iter::repeat_n(' ', pad / 2));
+                    line.push_str(&text);
+                    line.extend(std::iter::repeat_n(' ', pad - pad / 2));
+                }
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the character at a grid point with borders going in the given directions.
+fn corner(up: bool, down: bool, left: bool, right: bool, ascii: bool) -> char {
+    if ascii {
+        return match (up || down, left || right) {
+            (false, false) => ' ',
+            (true, false) => '|',
+            (false, true) => '-',
+            (true, true) => '+',
+        };
+    }
+    match (up, down, left, right) {
+        (false, false, false, false) => ' ',
+        (true, false, false, false) => '╵',
+        (false, true, false, false) => '╷',
+        (false, false, true, false) => '╴',
+        (false, false, false, true) => '╶',
+        (true, true, false, false) => '│',
+        (false, false, true, true) => '─',
+        (false, true, false, true) => '┌',
+        (false, true, true, false) => '┐',
+        (true, false, false, true) => '└',
+        (true, false, true, false) => '┘',
+        (true, true, false, true) => '├',
+        (true, true, true, false) => '┤',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┴',
+        (true, true, true, true) => '┼',
+    }
+}
+
+/// Width of a cell without labels.
+const CELL_WIDTH: usize = 3;
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_grid(f, self.size(), CELL_WIDTH, |cd| if self.get(cd) { Some((0, 0)) } else { None }, |_| String::new())
+    }
+}
+
+/// An `Answer` drawn with the name of the piece in each cell, returned by `Answer::with_names`.
+pub struct NamedAnswer<'a> {
+    answer: &'a Answer,
+    names: &'a [String],
+}
+
+impl Answer {
+    /// Returns `self` to be drawn with `names[piece]` written in each cell of `piece` (such as `Puzzle::piece_names`).
+    pub fn with_names<'a>(&'a self, names: &'a [String]) -> NamedAnswer<'a> {
+        NamedAnswer { answer: self, names }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, names: Option<&[String]>) -> fmt::Result {
+        let width = match names {
+            Some(names) => names.iter().map(|name| name.chars().count()).max().unwrap_or(0).max(1) + 2,
+            None => CELL_WIDTH,
+        };
+        let region = |cd| if self[cd] == UNFILLED { None } else { Some(self[cd]) };
+        let label = |cd| {
+            let (piece, _) = self[cd];
+            if self[cd] == BLOCKED {
+                "#".repeat(width)
+            } else if piece < 0 {
+                String::new()
+            } else {
+                names.map_or(String::new(), |names| names[piece as usize].clone())
+            }
+        };
+        write_grid(f, self.size(), width, region, label)
+    }
+}
+
+/// Blocked cells are filled with `#`, and unfilled cells are left blank.
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, None)
+    }
+}
+
+impl<'a> fmt::Display for NamedAnswer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.answer.write(f, Some(self.names))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let shape = Shape::from_grid(&["##.", ".##"]).unwrap();
+        assert_eq!(format!("{}", shape), "\
+┌───────┐
+│       │
+└───┐   └───┐
+    │       │
+    └───────┘
+");
+        assert_eq!(format!("{:#}", shape), "\
++-------+
+|       |
++---+   +---+
+    |       |
+    +-------+
+");
+
+        // an L-tromino, a domino, a blocked cell and an unfilled cell, in two layers
+        let mut answer = Answer::new(Coord { x: 2, y: 2, z: 2 });
+        for &(x, y, z, val) in &[(0, 0, 0, (0, 0)), (1, 0, 0, (0, 0)), (0, 1, 0, (0, 0)), (1, 1, 0, BLOCKED),
+                                  (0, 0, 1, (1, 0)), (1, 0, 1, (1, 1)), (1, 1, 1, (1, 1)), (0, 1, 1, UNFILLED)] {
+            answer[Coord { x, y, z }] = val;
+        }
+        assert_eq!(format!("{}", answer), "\
+┌───────┐
+│       │
+│   ┌───┤
+│   │###│
+└───┴───┘
+
+┌───┬───┐
+│   │   │
+└───┤   │
+    │   │
+    └───┘
+");
+        let names = vec!["L".to_string(), "I'".to_string()];
+        assert_eq!(format!("{:#}", answer.with_names(&names)), "\
++---------+
+| L    L  |
+|    +----+
+| L  |####|
++----+----+
+
++----+----+
+| I' | I' |
++----+    |
+     | I' |
+     +----+
+");
+    }
+}
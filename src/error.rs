@@ -0,0 +1,60 @@
+use super::*;
+
+use std::error::Error;
+use std::fmt;
+
+/// Reasons for which a puzzle cannot be solved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolymateError {
+    /// The target has no cells.
+    EmptyTarget,
+    /// The target has more cells than the bit set type can hold.
+    CapacityExceeded { cells: i32, capacity: i32 },
+    /// Rows of a grid have different lengths.
+    RaggedGrid,
+    /// The piece of this index has more cells than the target.
+    PieceLargerThanTarget(usize),
+    /// The piece of this index has no cells.
+    ZeroVolumePiece(usize),
+    /// The piece of this index has a negative number of copies.
+    NegativePieceCount(usize),
+    /// The pieces have fewer cells in total than the target.
+    VolumeMismatch { pieces: i32, target: i32 },
+    /// The number of orientation policies differs from the number of pieces.
+    OrientationsMismatch { pieces: usize, orientations: usize },
+    /// The number of piece names differs from the number of pieces.
+    PieceNamesMismatch { pieces: usize, names: usize },
+    /// The fixed placement of this index refers to no piece, uses more copies than available,
+    /// or does not fit in the target.
+    InvalidFixedPlacement(usize),
+    /// This blocked cell is not a cell of the target or is covered by a fixed placement.
+    InvalidBlockedCell(Coord),
+    /// A puzzle file is malformed at this line (1-origin).
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for PolymateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PolymateError::EmptyTarget => write!(f, "the target is empty"),
+            PolymateError::CapacityExceeded { cells, capacity } =>
+                write!(f, "the target has {} cells, but at most {} cells are supported", cells, capacity),
+            PolymateError::RaggedGrid => write!(f, "rows of the grid have different lengths"),
+            PolymateError::PieceLargerThanTarget(i) => write!(f, "piece #{} is larger than the target", i),
+            PolymateError::ZeroVolumePiece(i) => write!(f, "piece #{} is empty", i),
+            PolymateError::NegativePieceCount(i) => write!(f, "piece #{} has a negative count", i),
+            PolymateError::VolumeMismatch { pieces, target } =>
+                write!(f, "the pieces have {} cells in total, but the target has {} cells", pieces, target),
+            PolymateError::OrientationsMismatch { pieces, orientations } =>
+                write!(f, "{} orientation policies are given for {} pieces", orientations, pieces),
+            PolymateError::PieceNamesMismatch { pieces, names } =>
+                write!(f, "{} names are given for {} pieces", names, pieces),
+            PolymateError::InvalidFixedPlacement(i) => write!(f, "fixed placement #{} is invalid", i),
+            PolymateError::InvalidBlockedCell(cd) =>
+                write!(f, "cell ({}, {}, {}) cannot be blocked", cd.x, cd.y, cd.z),
+            PolymateError::Parse { line, ref message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for PolymateError {}
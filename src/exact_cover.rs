@@ -1,5 +1,8 @@
 use super::*;
 
+use std::ops::ControlFlow;
+use std::sync::Mutex;
+
 /// `1` entries and meta nodes in an exact cover problem instance.
 ///
 /// - Node indexing
@@ -17,24 +20,50 @@ struct Node {
     col: usize,
 }
 
-pub struct ExactCover {
+/// Dancing Links solver for the exact cover problem made of the placements in a `Dictionary`.
+///
+/// Each target cell is a primary column, which must be covered exactly once.
+/// Each piece type is a secondary column, which may be covered up to `piece_count` times:
+/// this column is removed only after all the copies of the piece are used.
+/// Therefore pieces with multiplicity and puzzles with spare pieces are supported.
+///
+/// Answers are deduplicated in the same way as `solve`, so the results of both are interchangeable.
+/// This solver is also available in `solve_with_options` as `Backend::DancingLinks`.
+#[derive(Clone)]
+pub struct ExactCover<'a, T: Bits + 'a> {
+    dic: &'a Dictionary<T>,
     nodes: Vec<Node>,
     column_count: Vec<usize>,
-    pub n_answers: u64,
+    column_capacity: Vec<i32>,
+    rows: Vec<(i32, i32, i32)>, // cell, piece, orientation (row `r` is `rows[r - 1]`)
+    row_head: Vec<usize>, // the first node of each row (row `r` is `row_head[r - 1]`)
+    selected_rows: Vec<usize>,
 }
 
-impl ExactCover {
-    pub fn from_dictionary(dic: &Dictionary<u64>) -> ExactCover {
+impl<'a, T: Bits> ExactCover<'a, T> {
+    pub fn from_dictionary(dic: &'a Dictionary<T>) -> ExactCover<'a, T> {
         let mut nodes = vec![];
         let mut col_last = vec![];
         let mut column_count = vec![];
+        let mut column_capacity = vec![];
+        let mut rows = vec![];
+        let mut row_head = vec![];
 
-        let n_col = dic.n_target_cells as usize + dic.piece_count.len() + 1;
+        let n_pieces = dic.piece_count.len();
+        let n_col = dic.n_target_cells as usize + n_pieces + 1;
 
         for i in 0..n_col {
+            // only the starting node and primary columns (i > n_pieces) are linked horizontally
+            let (left, right) = if i == 0 {
+                if n_col == n_pieces + 1 { (0, 0) } else { (n_col - 1, n_pieces + 1) }
+            } else if i <= n_pieces {
+                (i, i)
+            } else {
+                (if i == n_pieces + 1 { 0 } else { i - 1 }, if i == n_col - 1 { 0 } else { i + 1 })
+            };
             nodes.push(Node {
-                left: if i == 0 { n_col - 1 } else { i - 1 },
-                right: if i == n_col - 1 { 0 } else { i + 1 },
+                left,
+                right,
                 up: i,
                 down: i,
                 row: 0,
@@ -42,28 +71,32 @@ impl ExactCover {
             });
             col_last.push(i);
             column_count.push(0);
+            column_capacity.push(if 1 <= i && i <= n_pieces { dic.piece_count[i - 1] } else { 1 });
         }
 
         for c in 0..(dic.n_target_cells as usize) {
-            for p in 0..dic.piece_count.len() {
-                for &m in &dic.placements[c][p] {
+            for p in 0..n_pieces {
+                if dic.piece_count[p] == 0 { continue; }
+                for (ori, m) in dic.placements[c][p].iter().enumerate() {
                     let mut cols = vec![p + 1];
                     {
-                        let mut m = m;
-                        while m != 0 {
-                            let idx = m.trailing_zeros();
-                            m ^= 1u64 << (idx as u64);
-                            cols.push(dic.piece_count.len() + 1 + idx as usize);
+                        let mut m = m.clone();
+                        while !m.is_empty() {
+                            let idx = m.lowest_set_bit();
+                            m.unset(idx);
+                            cols.push(n_pieces + 1 + idx as usize);
                         }
                     }
+                    rows.push((c as i32, p as i32, ori as i32));
                     let base = nodes.len();
+                    row_head.push(base);
                     for i in 0..cols.len() {
                         nodes.push(Node {
                             left: if i == 0 { base + cols.len() - 1 } else { base + i - 1 },
                             right: if i == cols.len() - 1 { base } else { base + i + 1 },
                             up: col_last[cols[i]],
                             down: cols[i],
-                            row: 0,
+                            row: rows.len(),
                             col: cols[i],
                         });
                         nodes[col_last[cols[i]]].down = nodes.len() - 1;
@@ -81,14 +114,74 @@ impl ExactCover {
             assert_eq!(i, nodes[nodes[i].down].up);
         }
         ExactCover {
+            dic,
             nodes,
             column_count,
-            n_answers: 0u64,
+            column_capacity,
+            rows,
+            row_head,
+            selected_rows: vec![],
+        }
+    }
+
+    /// Enumerates all answers.
+    ///
+    /// Like `solve`, the initial placements of `Dictionary` are used for breaking the symmetry.
+    pub fn solve(&mut self) -> Answers {
+        let answer = Mutex::new(vec![]);
+        let callback = |_, a: &Answer, _| {
+            answer.lock().unwrap().push(a.clone());
+            ControlFlow::Continue(())
+        };
+        let options = SolveOptions::new();
+        let shared = SharedState::new();
+        let mut ctx = SearchContext::new(&options, &shared, &callback);
+
+        for i in 0..self.dic.initial_placement_id.len() {
+            self.search_initial(i, &mut ctx);
+        }
+
+        let mut answers = ctx.answers;
+        answers.answer = answer.into_inner().unwrap();
+        answers
+    }
+
+    /// Enumerates all answers starting from the `initial`-th initial placement of `Dictionary`.
+    pub(crate) fn search_initial(&mut self, initial: usize, ctx: &mut SearchContext) {
+        let dic = self.dic;
+        let mut initial_rows = vec![];
+        for &(cell, piece, ori) in &dic.initial_placement_id[initial] {
+            let row = self.rows.iter().position(|&r| r == (cell, piece, ori)).unwrap() + 1;
+            initial_rows.push(row);
+        }
+
+        // the first node of each row is in the column of the piece, so select by the second one
+        for &row in &initial_rows {
+            let i = self.nodes[self.row_head[row - 1]].right;
+            self.select_row(i);
+        }
+        // withhold the copies of the special pieces not placed
+        let mut withheld = vec![];
+        for p in 0..dic.piece_count.len() {
+            while self.column_capacity[p + 1] > dic.initial_piece_count[initial][p] {
+                self.use_column(p + 1);
+                withheld.push(p + 1);
+            }
+        }
+        ctx.symmetry = dic.initial_symmetry[initial];
+        self.search(ctx);
+        for &c in withheld.iter().rev() {
+            self.unuse_column(c);
+        }
+        for &row in initial_rows.iter().rev() {
+            let i = self.nodes[self.row_head[row - 1]].right;
+            self.unselect_row(i);
         }
     }
-    pub fn search(&mut self) {
+
+    fn search(&mut self, ctx: &mut SearchContext) {
         // find the pivot (lightest column)
-        let mut cand = (usize::max_value(), 0usize);
+        let mut cand = (usize::MAX, 0usize);
         {
             let mut i = self.nodes[0].right;
             while i != 0 {
@@ -100,8 +193,7 @@ impl ExactCover {
         if cand.0 == 0 { return; }
         let pivot = cand.1;
         if pivot == 0 {
-            // TODO: record answer
-            self.n_answers += 1;
+            self.save_answer(ctx);
             return;
         }
 
@@ -111,25 +203,15 @@ impl ExactCover {
         let mut i = self.nodes[pivot].down;
         while i != pivot {
             // choose the `row` with node `i`
-            {
-                let mut j = self.nodes[i].right;
-                while j != i {
-                    let c = self.nodes[j].col;
-                    self.purge_column_full(c);
-                    j = self.nodes[j].right;
-                }
-            }
+            if ctx.step() { break; }
+            self.cover_row(i);
+            self.selected_rows.push(self.nodes[i].row);
 
-            self.search();
+            self.search(ctx);
 
-            {
-                let mut j = self.nodes[i].left;
-                while j != i {
-                    let c = self.nodes[j].col;
-                    self.restore_column_full(c);
-                    j = self.nodes[j].left;
-                }
-            }
+            self.selected_rows.pop();
+            self.uncover_row(i);
+            if ctx.terminated { break; }
             i = self.nodes[i].down;
         }
 
@@ -137,6 +219,70 @@ impl ExactCover {
         self.restore_column_full(pivot);
     }
 
+    /// Use one more of column `c`, and remove it if its capacity is exhausted
+    fn use_column(&mut self, c: usize) {
+        self.column_capacity[c] -= 1;
+        if self.column_capacity[c] == 0 {
+            self.purge_column_full(c);
+        }
+    }
+
+    /// Undo `use_column`
+    fn unuse_column(&mut self, c: usize) {
+        if self.column_capacity[c] == 0 {
+            self.restore_column_full(c);
+        }
+        self.column_capacity[c] += 1;
+    }
+
+    /// Cover the columns of the row containing node `i`, except for the column of `i` itself
+    /// (which is supposed to be already removed)
+    fn cover_row(&mut self, i: usize) {
+        let mut j = self.nodes[i].right;
+        while j != i {
+            let c = self.nodes[j].col;
+            self.use_column(c);
+            j = self.nodes[j].right;
+        }
+    }
+
+    /// Undo `cover_row`
+    fn uncover_row(&mut self, i: usize) {
+        let mut j = self.nodes[i].left;
+        while j != i {
+            let c = self.nodes[j].col;
+            self.unuse_column(c);
+            j = self.nodes[j].left;
+        }
+    }
+
+    /// Choose the row containing node `i`, which must be in a primary column
+    fn select_row(&mut self, i: usize) {
+        let c = self.nodes[i].col;
+        self.purge_column_full(c);
+        self.cover_row(i);
+        self.selected_rows.push(self.nodes[i].row);
+    }
+
+    /// Undo `select_row`
+    fn unselect_row(&mut self, i: usize) {
+        self.selected_rows.pop();
+        self.uncover_row(i);
+        let c = self.nodes[i].col;
+        self.restore_column_full(c);
+    }
+
+    fn save_answer(&mut self, ctx: &mut SearchContext) {
+        let dic = self.dic;
+        let mut answer_raw = vec![(-1, -1); dic.n_target_cells as usize];
+        for &row in &self.selected_rows {
+            let (cell, piece, ori) = self.rows[row - 1];
+            answer_raw[cell as usize] = (piece, ori);
+        }
+
+        save_answer(dic, &answer_raw, ctx);
+    }
+
     /// Remove column `c` and all associated rows
     fn purge_column_full(&mut self, c: usize) {
         let mut i = self.nodes[c].down;
@@ -185,3 +331,80 @@ impl ExactCover {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_cover_answers() {
+        let problems = vec![
+            (Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 20, y: 3, z: 1 })), 2),
+            (Puzzle::with_names(library::soma_pieces(), Shape::filled(Coord { x: 3, y: 3, z: 3 })), 240),
+        ];
+        for &(ref problem, n_answers) in &problems {
+            let dic = Dictionary::<u64>::new(problem).unwrap();
+
+            let mut exact_cover = ExactCover::from_dictionary(&dic);
+            let dlx_answers = exact_cover.solve();
+            assert_eq!(dlx_answers.count, n_answers);
+            let mut dlx_answers = dlx_answers.answer;
+            dlx_answers.sort();
+
+            let mut answers = solve(problem).unwrap().answer;
+            answers.sort();
+
+            assert!(dlx_answers == answers);
+        }
+    }
+
+    #[test]
+    fn test_exact_cover_piece_count() {
+        let l_tromino = Shape::from_grid(&["#.", "##"]).unwrap();
+        let domino = Shape::from_grid(&["##"]).unwrap();
+        let problems = vec![
+            // multiple copies
+            Puzzle::new(vec![(l_tromino.clone(), 4)], Shape::filled(Coord { x: 4, y: 3, z: 1 })),
+            Puzzle::new(vec![(l_tromino.clone(), 2), (domino.clone(), 3)], Shape::filled(Coord { x: 4, y: 3, z: 1 })),
+            // spare pieces
+            Puzzle::new(vec![(l_tromino.clone(), 3), (domino.clone(), 5)], Shape::filled(Coord { x: 4, y: 3, z: 1 })),
+            Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 5, y: 4, z: 1 })),
+        ];
+        for problem in &problems {
+            let dic = Dictionary::<u64>::new(problem).unwrap();
+
+            let mut exact_cover = ExactCover::from_dictionary(&dic);
+            let mut dlx_answers = exact_cover.solve().answer;
+            dlx_answers.sort();
+
+            let mut answers = solve(problem).unwrap().answer;
+            answers.sort();
+
+            assert!(!answers.is_empty());
+            assert!(dlx_answers == answers);
+        }
+    }
+
+    #[test]
+    fn test_exact_cover_large_target() {
+        let mut target = Shape::filled(Coord { x: 8, y: 8, z: 1 });
+        for x in 3..5 {
+            for y in 3..5 {
+                target.set(Coord { x, y, z: 0 }, false);
+            }
+        }
+        let problem = Puzzle::with_names(library::pentominoes(), target);
+        let dic = Dictionary::<Bitset>::new(&problem).unwrap();
+
+        let mut exact_cover = ExactCover::from_dictionary(&dic);
+        let dlx_answers = exact_cover.solve();
+        assert_eq!(dlx_answers.count, 65);
+        let mut dlx_answers = dlx_answers.answer;
+        dlx_answers.sort();
+
+        let mut answers = solve(&problem).unwrap().answer;
+        answers.sort();
+
+        assert!(dlx_answers == answers);
+    }
+}
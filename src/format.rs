@@ -0,0 +1,384 @@
+//! A plain-text format of puzzles.
+
+use super::*;
+
+use std::fmt;
+
+fn parse_error(line: usize, message: &str) -> PolymateError {
+    PolymateError::Parse { line, message: message.to_string() }
+}
+
+struct Section {
+    line: usize,
+    name: Option<String>,
+    count: i32,
+    layers: Vec<Vec<String>>,
+}
+
+impl Section {
+    fn to_shape(&self) -> Result<Shape, PolymateError> {
+        let layers = &self.layers;
+        if layers.is_empty() {
+            return Err(parse_error(self.line, "no grid is given"));
+        }
+        let height = layers[0].len();
+        let width = layers[0][0].len();
+        for layer in layers {
+            if layer.len() != height || layer.iter().any(|row| row.len() != width) {
+                return Err(parse_error(self.line, "rows or layers of the grid have different sizes"));
+            }
+        }
+
+        let mut ret = Shape::new(Coord { x: width as i32, y: height as i32, z: layers.len() as i32 });
+        for (z, layer) in layers.iter().enumerate() {
+            for (y, row) in layer.iter().enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    ret.set(Coord { x: x as i32, y: y as i32, z: z as i32 }, c == '#');
+                }
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// A single-line section which refers to pieces by name, resolved after all pieces are read.
+enum Directive {
+    Orientation { name: String, policy: OrientationPolicy },
+    Fixed { name: String, transformation: Transformation, offset: Coord },
+    Blocked(Coord),
+}
+
+fn parse_int(line: usize, token: &str) -> Result<i32, PolymateError> {
+    token.parse::<i32>().map_err(|_| parse_error(line, "expected an integer"))
+}
+
+fn parse_coord(line: usize, tokens: &[&str]) -> Result<Coord, PolymateError> {
+    Ok(Coord { x: parse_int(line, tokens[0])?, y: parse_int(line, tokens[1])?, z: parse_int(line, tokens[2])? })
+}
+
+/// A transformation is written as its index in `TRANSFORMATIONS`.
+fn parse_transformation(line: usize, token: &str) -> Result<Transformation, PolymateError> {
+    match token.parse::<usize>() {
+        Ok(i) if i < TRANSFORMATIONS.len() => Ok(TRANSFORMATIONS[i]),
+        _ => Err(parse_error(line, "a transformation must be an integer from 0 to 47")),
+    }
+}
+
+fn transformation_index(transformation: &Transformation) -> usize {
+    TRANSFORMATIONS.iter().position(|t| t == transformation).unwrap()
+}
+
+fn parse_policy(line: usize, tokens: &[&str]) -> Result<OrientationPolicy, PolymateError> {
+    let policy = match tokens[0] {
+        "rotations" => OrientationPolicy::Rotations,
+        "free" => OrientationPolicy::Free,
+        "in-plane" => OrientationPolicy::InPlane,
+        "fixed" => OrientationPolicy::Fixed,
+        "explicit" => {
+            let transformations = tokens[1..].iter().map(|t| parse_transformation(line, t)).collect::<Result<Vec<_>, _>>()?;
+            return Ok(OrientationPolicy::Explicit(transformations));
+        }
+        _ => return Err(parse_error(line, "unknown orientation policy")),
+    };
+    if tokens.len() != 1 {
+        return Err(parse_error(line, "only `explicit` takes transformations"));
+    }
+    Ok(policy)
+}
+
+impl Puzzle {
+    /// Parses a puzzle written in the following format.
+    ///
+    /// ```text
+    /// // comments start with two slashes
+    /// piece F
+    /// .##
+    /// ##.
+    /// .#.
+    ///
+    /// piece V 2
+    /// #..
+    /// #..
+    /// ###
+    ///
+    /// target
+    /// ###
+    /// ###
+    ///
+    /// ###
+    /// ###
+    ///
+    /// orientation F in-plane
+    /// fixed V 0 0 0 0
+    /// blocked 2 1 1
+    /// ```
+    ///
+    /// A section starts with a header, either `piece <name> [<count>]` (the count defaults to 1) or `target`,
+    /// which is followed by the grid of the shape: `#` for a filled cell and `.` for an empty one.
+    /// Characters of a row are along the x-axis and rows are along the y-axis.
+    /// Layers along the z-axis are separated by blank lines.
+    ///
+    /// The other sections take a single line:
+    ///
+    /// - `orientation <name> <policy>` sets the orientation policy of a piece (`rotations` by default) to
+    ///   `rotations`, `free`, `in-plane`, `fixed` or `explicit <transformation>...`.
+    /// - `fixed <name> <transformation> <x> <y> <z>` places a copy of a piece in advance.
+    /// - `blocked <x> <y> <z>` blocks a cell of the target.
+    ///
+    /// A transformation is written as its index in `TRANSFORMATIONS`, where `0` is the identity.
+    pub fn parse(text: &str) -> Result<Puzzle, PolymateError> {
+        let mut sections: Vec<Section> = vec![];
+        let mut directives = vec![];
+        let mut in_grid_section = false;
+        let mut n_lines = 0;
+
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            n_lines = line_no;
+            let line = line.trim_end();
+
+            if line.starts_with("//") {
+                continue;
+            }
+            if line.is_empty() {
+                // a blank line ends the current layer
+                if let Some(section) = sections.last_mut() {
+                    if section.layers.last().is_some_and(|layer| !layer.is_empty()) {
+                        section.layers.push(vec![]);
+                    }
+                }
+                continue;
+            }
+            if line.chars().all(|c| c == '#' || c == '.') {
+                match sections.last_mut() {
+                    Some(section) if in_grid_section => section.layers.last_mut().unwrap().push(line.to_string()),
+                    _ => return Err(parse_error(line_no, "a grid row must follow a `piece` or `target` header")),
+                }
+                continue;
+            }
+
+            if line.starts_with('#') || line.starts_with('.') {
+                return Err(parse_error(line_no, "grid rows may contain only `#` and `.`"));
+            }
+
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            let section = match tokens[0] {
+                "piece" => {
+                    if tokens.len() < 2 || tokens.len() > 3 {
+                        return Err(parse_error(line_no, "expected `piece <name> [<count>]`"));
+                    }
+                    let count = match tokens.get(2) {
+                        Some(c) => match c.parse::<i32>() {
+                            Ok(c) if c >= 0 => c,
+                            _ => return Err(parse_error(line_no, "the count must be a non-negative integer")),
+                        },
+                        None => 1,
+                    };
+                    if sections.iter().any(|s| s.name.as_deref() == Some(tokens[1])) {
+                        return Err(parse_error(line_no, "duplicate piece name"));
+                    }
+                    Section { line: line_no, name: Some(tokens[1].to_string()), count, layers: vec![vec![]] }
+                }
+                "target" => {
+                    if tokens.len() != 1 {
+                        return Err(parse_error(line_no, "expected `target`"));
+                    }
+                    if sections.iter().any(|s| s.name.is_none()) {
+                        return Err(parse_error(line_no, "the target is given more than once"));
+                    }
+                    Section { line: line_no, name: None, count: 0, layers: vec![vec![]] }
+                }
+                "orientation" => {
+                    if tokens.len() < 3 {
+                        return Err(parse_error(line_no, "expected `orientation <name> <policy>`"));
+                    }
+                    let policy = parse_policy(line_no, &tokens[2..])?;
+                    directives.push((line_no, Directive::Orientation { name: tokens[1].to_string(), policy }));
+                    in_grid_section = false;
+                    continue;
+                }
+                "fixed" => {
+                    if tokens.len() != 6 {
+                        return Err(parse_error(line_no, "expected `fixed <name> <transformation> <x> <y> <z>`"));
+                    }
+                    let transformation = parse_transformation(line_no, tokens[2])?;
+                    let offset = parse_coord(line_no, &tokens[3..])?;
+                    directives.push((line_no, Directive::Fixed { name: tokens[1].to_string(), transformation, offset }));
+                    in_grid_section = false;
+                    continue;
+                }
+                "blocked" => {
+                    if tokens.len() != 4 {
+                        return Err(parse_error(line_no, "expected `blocked <x> <y> <z>`"));
+                    }
+                    directives.push((line_no, Directive::Blocked(parse_coord(line_no, &tokens[1..])?)));
+                    in_grid_section = false;
+                    continue;
+                }
+                _ => return Err(parse_error(line_no, "unknown header")),
+            };
+            sections.push(section);
+            in_grid_section = true;
+        }
+
+        let mut pieces = vec![];
+        let mut target = None;
+        for section in &mut sections {
+            if section.layers.last().is_some_and(|layer| layer.is_empty()) {
+                section.layers.pop();
+            }
+            let shape = section.to_shape()?;
+            match section.name {
+                Some(ref name) => pieces.push((name.clone(), shape, section.count)),
+                None => target = Some(shape),
+            }
+        }
+        let mut puzzle = match target {
+            Some(target) => Puzzle::with_names(pieces, target),
+            None => return Err(parse_error(n_lines + 1, "the target is missing")),
+        };
+
+        for (line_no, directive) in directives {
+            let piece_index = |name: &str| match puzzle.piece_names.iter().position(|n| n == name) {
+                Some(i) => Ok(i),
+                None => Err(parse_error(line_no, "unknown piece name")),
+            };
+            match directive {
+                Directive::Orientation { name, policy } => {
+                    let i = piece_index(&name)?;
+                    puzzle.orientations[i] = policy;
+                }
+                Directive::Fixed { name, transformation, offset } => {
+                    let piece = piece_index(&name)?;
+                    puzzle.fixed.push(FixedPlacement { piece, transformation, offset });
+                }
+                Directive::Blocked(cd) => puzzle.blocked.push(cd),
+            }
+        }
+        Ok(puzzle)
+    }
+}
+
+fn write_shape(f: &mut fmt::Formatter, shape: &Shape) -> fmt::Result {
+    let size = shape.size();
+    for z in 0..size.z {
+        if z > 0 {
+            writeln!(f)?;
+        }
+        for y in 0..size.y {
+            for x in 0..size.x {
+                write!(f, "{}", if shape.get(Coord { x, y, z }) { '#' } else { '.' })?;
+            }
+            writeln!(f)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `self` in the format read by `Puzzle::parse`.
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for i in 0..self.pieces.len() {
+            let (ref shape, count) = self.pieces[i];
+            writeln!(f, "piece {} {}", self.piece_names[i], count)?;
+            write_shape(f, shape)?;
+            writeln!(f)?;
+        }
+        writeln!(f, "target")?;
+        write_shape(f, &self.target)?;
+
+        let mut directives = vec![];
+        for i in 0..self.pieces.len() {
+            let policy = match self.orientations[i] {
+                OrientationPolicy::Rotations => continue,
+                OrientationPolicy::Free => "free".to_string(),
+                OrientationPolicy::InPlane => "in-plane".to_string(),
+                OrientationPolicy::Fixed => "fixed".to_string(),
+                OrientationPolicy::Explicit(ref transformations) => {
+                    let indices = transformations.iter().map(|t| format!(" {}", transformation_index(t))).collect::<String>();
+                    format!("explicit{}", indices)
+                }
+            };
+            directives.push(format!("orientation {} {}", self.piece_names[i], policy));
+        }
+        for fp in &self.fixed {
+            let cd = fp.offset;
+            directives.push(format!("fixed {} {} {} {} {}", self.piece_names[fp.piece], transformation_index(&fp.transformation), cd.x, cd.y, cd.z));
+        }
+        for cd in &self.blocked {
+            directives.push(format!("blocked {} {} {}", cd.x, cd.y, cd.z));
+        }
+        if !directives.is_empty() {
+            writeln!(f)?;
+            for d in directives {
+                writeln!(f, "{}", d)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "\
+// two layers of 2x3
+piece L 2
+#.
+##
+
+piece I
+###
+
+target
+###
+###
+
+###
+###
+";
+        let puzzle = Puzzle::parse(text).unwrap();
+        assert_eq!(puzzle.piece_names, vec!["L".to_string(), "I".to_string()]);
+        assert_eq!(puzzle.pieces[0].0, Shape::from_grid(&["#.", "##"]).unwrap());
+        assert_eq!(puzzle.pieces[0].1, 2);
+        assert_eq!(puzzle.pieces[1].1, 1);
+        assert_eq!(puzzle.target, Shape::filled(Coord { x: 3, y: 2, z: 2 }));
+
+        assert_eq!(Puzzle::parse(&puzzle.to_string()).unwrap(), puzzle);
+
+        // orientation policies, fixed placements and blocked cells
+        let text = format!("{}\norientation L in-plane\norientation I explicit 0 2\nfixed L 1 0 0 0\nblocked 2 1 1\n", text);
+        let mut puzzle = Puzzle::parse(&text).unwrap();
+        assert_eq!(puzzle.orientations, vec![OrientationPolicy::InPlane, OrientationPolicy::Explicit(vec![TRANSFORMATIONS[0], TRANSFORMATIONS[2]])]);
+        assert_eq!(puzzle.fixed, vec![FixedPlacement { piece: 0, transformation: TRANSFORMATIONS[1], offset: Coord { x: 0, y: 0, z: 0 } }]);
+        assert_eq!(puzzle.blocked, vec![Coord { x: 2, y: 1, z: 1 }]);
+        assert_eq!(Puzzle::parse(&puzzle.to_string()).unwrap(), puzzle);
+        puzzle.orientations = vec![OrientationPolicy::Free, OrientationPolicy::Fixed];
+        assert_eq!(Puzzle::parse(&puzzle.to_string()).unwrap(), puzzle);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let line_of = |text: &str| match Puzzle::parse(text) {
+            Err(PolymateError::Parse { line, .. }) => line,
+            _ => panic!(),
+        };
+        assert_eq!(line_of("##\ntarget\n##"), 1);
+        assert_eq!(line_of("piece A x\n#\ntarget\n#"), 1);
+        assert_eq!(line_of("piece A\n#\npiece A\n#\ntarget\n#"), 3);
+        assert_eq!(line_of("piece A\n#\ntarget\n##\n#"), 3);
+        assert_eq!(line_of("piece A\n#\ntarget\n##\n\n#"), 3);
+        assert_eq!(line_of("piece A\n#\ntarget\n#\ntarget\n#"), 5);
+        assert_eq!(line_of("piece A\n#\n"), 3);
+        assert_eq!(line_of("piece A\n#\ntarget\n#x"), 4);
+        assert_eq!(line_of("piece A\n#\ntarget\n#\norientation B free"), 5);
+        assert_eq!(line_of("piece A\n#\ntarget\n#\norientation A upside-down"), 5);
+        assert_eq!(line_of("piece A\n#\ntarget\n#\norientation A fixed 0"), 5);
+        assert_eq!(line_of("piece A\n#\ntarget\n#\nfixed A 48 0 0 0"), 5);
+        assert_eq!(line_of("piece A\n#\ntarget\n#\nblocked 0 0"), 5);
+        assert_eq!(line_of("piece A\n#\ntarget\n#\nblocked 0 0 0\n#"), 6);
+    }
+}
@@ -0,0 +1,150 @@
+//! Enumeration of polyominoes and polycubes.
+
+use super::*;
+
+/// Which shapes are regarded as the same one when enumerating shapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Equivalence {
+    /// Shapes are identified up to rotations and reflections.
+    Free,
+    /// Shapes are identified up to rotations; mirror images are distinct.
+    OneSided,
+    /// Shapes are identified up to translations only.
+    Fixed,
+}
+
+/// Returns all polyominoes of `n` cells in the xy-plane, sorted.
+///
+/// Each shape is the smallest of its equivalent shapes (in the order of `Shape`), so the result is unique
+/// under `equivalence`. Rotations and reflections are those within the plane.
+pub fn polyominoes(n: i32, equivalence: Equivalence) -> Vec<Shape> {
+    enumerate(n, false, equivalence)
+}
+
+/// Returns all polycubes of `n` cells, sorted.
+///
+/// Each shape is the smallest of its equivalent shapes (in the order of `Shape`), so the result is unique
+/// under `equivalence`.
+pub fn polycubes(n: i32, equivalence: Equivalence) -> Vec<Shape> {
+    enumerate(n, true, equivalence)
+}
+
+/// Returns the transformations under which shapes are identified.
+fn equivalences(solid: bool, equivalence: Equivalence) -> Vec<Transformation> {
+    let candidates: &[Transformation] = match equivalence {
+        Equivalence::Free => &TRANSFORMATIONS,
+        Equivalence::OneSided => &ROTATIONS,
+        Equivalence::Fixed => &TRANSFORMATIONS[..1],
+    };
+    candidates.iter().cloned().filter(|t| solid || t.is_in_plane()).collect()
+}
+
+fn canonical(shape: &Shape, transformations: &[Transformation]) -> Shape {
+    let mut ret = shape.clone();
+    for &t in transformations {
+        let cand = shape.trans(t);
+        if cand < ret {
+            ret = cand;
+        }
+    }
+    ret
+}
+
+/// Shrinks `shape` to the bounding box of its cells.
+fn trim(shape: &Shape) -> Shape {
+    let size = shape.size();
+    let mut lo = size;
+    let mut hi = Coord { x: 0, y: 0, z: 0 };
+    for cd in size {
+        if shape.get(cd) {
+            lo = Coord { x: lo.x.min(cd.x), y: lo.y.min(cd.y), z: lo.z.min(cd.z) };
+            hi = Coord { x: hi.x.max(cd.x + 1), y: hi.y.max(cd.y + 1), z: hi.z.max(cd.z + 1) };
+        }
+    }
+    let mut ret = Shape::new(hi - lo);
+    for cd in hi - lo {
+        ret.set(cd, shape.get(cd + lo));
+    }
+    ret
+}
+
+fn enumerate(n: i32, solid: bool, equivalence: Equivalence) -> Vec<Shape> {
+    if n <= 0 {
+        return vec![];
+    }
+    let transformations = equivalences(solid, equivalence);
+    let margin = Coord { x: 1, y: 1, z: if solid { 1 } else { 0 } };
+    let neighbors = [
+        Coord { x: 1, y: 0, z: 0 },
+        Coord { x: -1, y: 0, z: 0 },
+        Coord { x: 0, y: 1, z: 0 },
+        Coord { x: 0, y: -1, z: 0 },
+        Coord { x: 0, y: 0, z: 1 },
+        Coord { x: 0, y: 0, z: -1 },
+    ];
+
+    let mut shapes = vec![Shape::filled(Coord { x: 1, y: 1, z: 1 })];
+    for _ in 1..n {
+        let mut next = vec![];
+        for shape in &shapes {
+            let size = shape.size();
+            let padded_size = size + margin + margin;
+            let mut padded = Shape::new(padded_size);
+            for cd in size {
+                padded.set(cd + margin, shape.get(cd));
+            }
+            for cd in padded_size {
+                if padded.get(cd) {
+                    continue;
+                }
+                let is_adjacent = neighbors.iter().any(|&d| {
+                    let nb = cd + d;
+                    0 <= nb.x && nb.x < padded_size.x && 0 <= nb.y && nb.y < padded_size.y && 0 <= nb.z && nb.z < padded_size.z && padded.get(nb)
+                });
+                if is_adjacent {
+                    let mut grown = padded.clone();
+                    grown.set(cd, true);
+                    next.push(canonical(&trim(&grown), &transformations));
+                }
+            }
+        }
+        next.sort();
+        next.dedup();
+        shapes = next;
+    }
+    shapes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(solid: bool, equivalence: Equivalence, max_n: i32) -> Vec<usize> {
+        (1..=max_n).map(|n| enumerate(n, solid, equivalence).len()).collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn test_polyominoes() {
+        assert_eq!(counts(false, Equivalence::Free, 7), vec![1, 1, 2, 5, 12, 35, 108]);
+        assert_eq!(counts(false, Equivalence::OneSided, 6), vec![1, 1, 2, 7, 18, 60]);
+        assert_eq!(counts(false, Equivalence::Fixed, 6), vec![1, 2, 6, 19, 63, 216]);
+        assert!(polyominoes(0, Equivalence::Free).is_empty());
+
+        let pentominoes = polyominoes(5, Equivalence::Free);
+        assert!(pentominoes.iter().all(|s| s.volume() == 5 && s.size().z == 1));
+        assert!(pentominoes.contains(&canonical(&Shape::from_grid(&["#.#", "###"]).unwrap(), &equivalences(false, Equivalence::Free))));
+    }
+
+    #[test]
+    fn test_polycubes() {
+        assert_eq!(counts(true, Equivalence::Free, 5), vec![1, 1, 2, 7, 23]);
+        assert_eq!(counts(true, Equivalence::OneSided, 5), vec![1, 1, 2, 8, 29]);
+        assert_eq!(counts(true, Equivalence::Fixed, 4), vec![1, 3, 15, 86]);
+
+        let mut tetracubes = library::tetracubes().into_iter().map(|p| p.1.canonize()).collect::<Vec<_>>();
+        tetracubes.sort();
+        let mut generated = polycubes(4, Equivalence::OneSided).iter().map(|s| s.canonize()).collect::<Vec<_>>();
+        generated.sort();
+        assert_eq!(generated, tetracubes);
+    }
+}
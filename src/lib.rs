@@ -5,6 +5,17 @@ mod solver;
 mod answer;
 mod exact_cover;
 mod bits;
+mod options;
+mod context;
+mod most_constrained;
+mod packing;
+mod error;
+mod format;
+mod generator;
+mod svg;
+mod display;
+mod mesh;
+pub mod library;
 
 pub use puzzle::*;
 pub use trans::*;
@@ -12,4 +23,13 @@ pub use dictionary::*;
 pub use solver::*;
 pub use answer::*;
 pub use exact_cover::*;
-use bits::*;
+pub use options::*;
+use context::*;
+use most_constrained::*;
+use packing::*;
+pub use bits::*;
+pub use error::*;
+pub use generator::*;
+pub use svg::*;
+pub use display::*;
+pub use mesh::*;
@@ -0,0 +1,308 @@
+//! Standard piece sets and classic puzzles.
+//!
+//! Piece sets are lists of `(name, shape, count)` to be passed to `Puzzle::with_names`, or to
+//! `Puzzle::with_orientation` with `OrientationPolicy::InPlane` for the one-sided sets.
+//! Shapes on the square grid only are available (no polyiamonds or polyhexes).
+
+use super::*;
+
+/// A list of named pieces, each with one copy.
+pub type PieceSet = Vec<(String, Shape, i32)>;
+
+fn planar_set(pieces: &[(&str, &[&str])]) -> PieceSet {
+    pieces.iter().map(|&(name, grid)| (name.to_string(), Shape::from_grid(grid).unwrap(), 1)).collect()
+}
+
+fn solid_set(pieces: &[(&str, &[&[&str]])]) -> PieceSet {
+    pieces.iter().map(|&(name, layers)| (name.to_string(), shape_from_layers(layers), 1)).collect()
+}
+
+/// Builds a shape whose `z`-th layer is given by `layers[z]` in the same way as `Shape::from_grid`.
+fn shape_from_layers(layers: &[&[&str]]) -> Shape {
+    let size = Coord { x: layers[0][0].len() as i32, y: layers[0].len() as i32, z: layers.len() as i32 };
+    let mut ret = Shape::new(size);
+    for cd in size {
+        ret.set(cd, layers[cd.z as usize][cd.y as usize].as_bytes()[cd.x as usize] == b'#');
+    }
+    ret
+}
+
+/// Adds the mirror image of each planar piece which cannot be rotated in the plane into it, named with a `'` appended.
+fn with_mirror_images(pieces: PieceSet) -> PieceSet {
+    let mut ret = vec![];
+    for (name, shape, count) in pieces {
+        let mirror = shape.trans(Transformation::id().flip_x());
+        let mut rot = Transformation::id();
+        let mut is_chiral = true;
+        for _ in 0..4 {
+            is_chiral &= shape.trans(rot) != mirror;
+            rot = rot.rotate_z_axis();
+        }
+        ret.push((name.clone(), shape, count));
+        if is_chiral {
+            ret.push((format!("{}'", name), mirror, count));
+        }
+    }
+    ret
+}
+
+pub fn monominoes() -> PieceSet {
+    planar_set(&[("M", &["#"])])
+}
+
+pub fn dominoes() -> PieceSet {
+    planar_set(&[("D", &["##"])])
+}
+
+pub fn trominoes() -> PieceSet {
+    planar_set(&[("I", &["###"]), ("V", &["##", "#."])])
+}
+
+pub fn tetrominoes() -> PieceSet {
+    planar_set(&[
+        ("I", &["####"]),
+        ("L", &["###", "#.."]),
+        ("O", &["##", "##"]),
+        ("S", &[".##", "##."]),
+        ("T", &["###", ".#."]),
+    ])
+}
+
+/// Tetrominoes with mirror images distinguished, named as in Tetris. They must not be turned over,
+/// so puzzles should place them under `OrientationPolicy::InPlane`.
+pub fn one_sided_tetrominoes() -> PieceSet {
+    planar_set(&[
+        ("I", &["####"]),
+        ("J", &["###", "..#"]),
+        ("L", &["###", "#.."]),
+        ("O", &["##", "##"]),
+        ("S", &[".##", "##."]),
+        ("T", &["###", ".#."]),
+        ("Z", &["##.", ".##"]),
+    ])
+}
+
+pub fn pentominoes() -> PieceSet {
+    planar_set(&[
+        ("F", &[".##", "##.", ".#."]),
+        ("I", &["#####"]),
+        ("L", &["####", "#..."]),
+        ("N", &["##..", ".###"]),
+        ("P", &["##", "##", "#."]),
+        ("T", &["###", ".#.", ".#."]),
+        ("U", &["#.#", "###"]),
+        ("V", &["#..", "#..", "###"]),
+        ("W", &["#..", "##.", ".##"]),
+        ("X", &[".#.", "###", ".#."]),
+        ("Y", &["####", ".#.."]),
+        ("Z", &["##.", ".#.", ".##"]),
+    ])
+}
+
+/// Pentominoes with mirror images distinguished; the mirror image of `F` is named `F'` and so on.
+/// Like `one_sided_tetrominoes`, they are to be placed under `OrientationPolicy::InPlane`.
+pub fn one_sided_pentominoes() -> PieceSet {
+    with_mirror_images(pentominoes())
+}
+
+pub fn hexominoes() -> PieceSet {
+    planar_set(&[
+        ("H1", &["######"]),
+        ("H2", &["#####", "#...."]),
+        ("H3", &["#####", ".#..."]),
+        ("H4", &["#####", "..#.."]),
+        ("H5", &["####", "##.."]),
+        ("H6", &["####", "#.#."]),
+        ("H7", &["####", "#..#"]),
+        ("H8", &["####", "#...", "#..."]),
+        ("H9", &["####", ".##."]),
+        ("H10", &["####", ".#..", ".#.."]),
+        ("H11", &["####.", "...##"]),
+        ("H12", &["###", "###"]),
+        ("H13", &["###", "##.", "#.."]),
+        ("H14", &["###", "##.", ".#."]),
+        ("H15", &["###.", "#.##"]),
+        ("H16", &["###", "#.#", "#.."]),
+        ("H17", &["###.", ".###"]),
+        ("H18", &["###", ".#.", "##."]),
+        ("H19", &["###", ".#.", ".#.", ".#."]),
+        ("H20", &["###..", "..###"]),
+        ("H21", &["###.", "..##", "..#."]),
+        ("H22", &["###.", "..##", "...#"]),
+        ("H23", &["###.", "..#.", "..##"]),
+        ("H24", &["##.", "###", ".#."]),
+        ("H25", &["##.", "###", "..#"]),
+        ("H26", &["##..", ".###", ".#.."]),
+        ("H27", &["##..", ".###", "..#."]),
+        ("H28", &["##..", ".###", "...#"]),
+        ("H29", &["##.", ".##", "##."]),
+        ("H30", &["##.", ".##", ".#.", ".#."]),
+        ("H31", &["##..", ".##.", "..##"]),
+        ("H32", &["##.", ".#.", ".##", ".#."]),
+        ("H33", &["##.", ".#.", ".#.", ".##"]),
+        ("H34", &[".#..", "####", ".#.."]),
+        ("H35", &[".#..", "####", "..#."]),
+    ])
+}
+
+/// Hexominoes with mirror images distinguished, named as in `one_sided_pentominoes`.
+pub fn one_sided_hexominoes() -> PieceSet {
+    with_mirror_images(hexominoes())
+}
+
+/// The 8 tetracubes distinct under rotations: the 5 tetrominoes, the two screws `A` and `B` and the tripod `P`.
+pub fn tetracubes() -> PieceSet {
+    let mut ret = tetrominoes();
+    ret.extend(solid_set(&[
+        ("A", &[&["#.", "#."], &["##", ".."]]),
+        ("B", &[&["#.", "##"], &["#.", ".."]]),
+        ("P", &[&["##", "#."], &["#.", ".."]]),
+    ]));
+    ret
+}
+
+/// The 29 pentacubes distinct under rotations: the 12 pentominoes and 17 non-planar ones named `C1` to `C11`,
+/// where the mirror image of a chiral piece has `'` appended.
+pub fn pentacubes() -> PieceSet {
+    let mut ret = pentominoes();
+    ret.extend(solid_set(&[
+        ("C1", &[&["##", "#."], &["#.", ".."], &["#.", ".."]]),
+        ("C2", &[&["#.", "#."], &["##", ".."], &["#.", ".."]]),
+        ("C2'", &[&["##", ".."], &["#.", "#."], &["#.", ".."]]),
+        ("C3", &[&["#.", "#."], &["#.", ".."], &["##", ".."]]),
+        ("C3'", &[&["##", ".."], &["#.", ".."], &["#.", "#."]]),
+        ("C4", &[&["#.", "##"], &["#.", ".."], &["#.", ".."]]),
+        ("C4'", &[&["#.", ".."], &["#.", ".."], &["#.", "##"]]),
+        ("C5", &[&["#.", ".."], &["##", "#."], &["#.", ".."]]),
+        ("C6", &[&["#.", ".."], &["#.", "##"], &["#.", ".."]]),
+        ("C7", &[&["##", "#."], &["#.", "#."]]),
+        ("C8", &[&["#.", "#."], &["##", ".."], &[".#", ".."]]),
+        ("C8'", &[&["#.", "##", ".#"], &["#.", "..", ".."]]),
+        ("C9", &[&["#.", "##"], &["##", ".."]]),
+        ("C10", &[&["#.", ".."], &["##", "#."], &["..", "#."]]),
+        ("C10'", &[&["#.", ".."], &["##", "#."], &[".#", ".."]]),
+        ("C11", &[&["#.", ".."], &["#.", "##"], &["..", ".#"]]),
+        ("C11'", &[&["#.", ".."], &["##", ".#"], &["..", ".#"]]),
+    ]));
+    ret
+}
+
+/// The 7 pieces of the Soma cube.
+pub fn soma_pieces() -> PieceSet {
+    let mut ret = planar_set(&[
+        ("V", &["##", "#."]),
+        ("L", &["###", "#.."]),
+        ("T", &["###", ".#."]),
+        ("Z", &["##.", ".##"]),
+    ]);
+    ret.extend(solid_set(&[
+        ("A", &[&["#.", "#."], &["##", ".."]]),
+        ("B", &[&["#.", "##"], &["#.", ".."]]),
+        ("P", &[&["##", "#."], &["#.", ".."]]),
+    ]));
+    ret
+}
+
+/// A well-known puzzle with its number of answers (up to symmetry), usable as a regression oracle.
+pub struct Classic {
+    pub name: &'static str,
+    pub puzzle: Puzzle,
+    pub answers: u64,
+}
+
+fn classic(name: &'static str, pieces: PieceSet, target: Shape, answers: u64) -> Classic {
+    Classic { name, puzzle: Puzzle::with_names(pieces, target), answers }
+}
+
+fn one_sided_classic(name: &'static str, pieces: PieceSet, target: Shape, answers: u64) -> Classic {
+    Classic { name, puzzle: Puzzle::with_orientation(pieces, target, OrientationPolicy::InPlane), answers }
+}
+
+fn box_target(x: i32, y: i32, z: i32) -> Shape {
+    Shape::filled(Coord { x, y, z })
+}
+
+/// The 8x8 square without the central 2x2 square.
+fn square_without_center() -> Shape {
+    let mut ret = box_target(8, 8, 1);
+    for x in 3..5 {
+        for y in 3..5 {
+            ret.set(Coord { x, y, z: 0 }, false);
+        }
+    }
+    ret
+}
+
+/// The classic puzzles built from the piece sets above.
+///
+/// Puzzles using an ad hoc selection of pieces, such as the Bedlam cube, are not included yet.
+pub fn classics() -> Vec<Classic> {
+    vec![
+        classic("pentomino-6x10", pentominoes(), box_target(10, 6, 1), 2339),
+        classic("pentomino-5x12", pentominoes(), box_target(12, 5, 1), 1010),
+        classic("pentomino-4x15", pentominoes(), box_target(15, 4, 1), 368),
+        classic("pentomino-3x20", pentominoes(), box_target(20, 3, 1), 2),
+        classic("pentomino-8x8-without-center", pentominoes(), square_without_center(), 65),
+        classic("pentomino-3x4x5", pentominoes(), box_target(5, 4, 3), 3940),
+        classic("pentomino-2x5x6", pentominoes(), box_target(6, 5, 2), 264),
+        classic("pentomino-2x3x10", pentominoes(), box_target(10, 3, 2), 12),
+        classic("soma-cube", soma_pieces(), box_target(3, 3, 3), 240),
+        one_sided_classic("one-sided-pentomino-3x30", one_sided_pentominoes(), box_target(30, 3, 1), 46),
+    ]
+}
+
+/// Returns the classic puzzle named `name`.
+pub fn classic_by_name(name: &str) -> Option<Classic> {
+    classics().into_iter().find(|c| c.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn n_distinct(pieces: &PieceSet) -> usize {
+        let mut shapes = pieces.iter().map(|p| p.1.canonize()).collect::<Vec<_>>();
+        shapes.sort();
+        shapes.dedup();
+        shapes.len()
+    }
+
+    #[test]
+    fn test_piece_sets() {
+        assert_eq!(n_distinct(&tetrominoes()), 5);
+        assert_eq!(n_distinct(&pentominoes()), 12);
+        assert_eq!(n_distinct(&hexominoes()), 35);
+        assert_eq!(n_distinct(&tetracubes()), 8);
+        assert_eq!(n_distinct(&pentacubes()), 29);
+        assert_eq!(n_distinct(&soma_pieces()), 7);
+        assert_eq!(one_sided_tetrominoes().len(), 7);
+        assert_eq!(one_sided_pentominoes().len(), 18);
+        assert!(one_sided_pentominoes().iter().any(|p| p.0 == "F'"));
+        assert!(!one_sided_pentominoes().iter().any(|p| p.0 == "T'"));
+        assert_eq!(one_sided_hexominoes().len(), 60);
+        assert_eq!(n_distinct(&one_sided_hexominoes()), 35);
+    }
+
+    #[test]
+    fn test_one_sided() {
+        let pieces = one_sided_tetrominoes().into_iter().filter(|p| p.0 != "T").collect::<Vec<_>>();
+        let count = |puzzle: Puzzle| solve(&puzzle).unwrap().count;
+
+        // turning the pieces over gives more answers
+        assert_eq!(count(Puzzle::with_orientation(pieces.clone(), box_target(6, 4, 1), OrientationPolicy::InPlane)), 1);
+        assert_eq!(count(Puzzle::with_names(pieces.clone(), box_target(6, 4, 1))), 22);
+        assert_eq!(count(Puzzle::with_orientation(pieces.clone(), box_target(8, 3, 1), OrientationPolicy::InPlane)), 2);
+        assert_eq!(count(Puzzle::with_names(pieces, box_target(8, 3, 1))), 24);
+
+        let c = classic_by_name("one-sided-pentomino-3x30").unwrap();
+        assert!(c.puzzle.orientations.iter().all(|o| *o == OrientationPolicy::InPlane));
+    }
+
+    #[test]
+    fn test_classics() {
+        for name in ["pentomino-3x20", "pentomino-8x8-without-center", "pentomino-2x3x10", "soma-cube"] {
+            let c = classic_by_name(name).unwrap();
+            assert_eq!(solve(&c.puzzle).unwrap().count, c.answers, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Export of shapes and answers as 3D meshes (Wavefront OBJ and STL).
+//!
+//! Cell `(x, y, z)` occupies the cube from `(x, y, z)` to `(x + 1, y + 1, z + 1)` times `MeshOptions::cell_size`.
+//! Faces between cells of the same piece are dropped, and the coplanar faces left are merged into rectangles.
+//! Corners of rectangles lying on an edge of another rectangle are added to that edge as well, so that
+//! the triangles of a surface meet edge to edge; the surface is 2-manifold unless two cells of a piece
+//! meet only along an edge. Faces are wound counterclockwise when seen from outside.
+
+use super::*;
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Options controlling the mesh export.
+#[derive(Clone)]
+pub struct MeshOptions {
+    /// Side length of a cell.
+    pub cell_size: f64,
+    /// Each piece instance of an answer is moved away from the center of the answer by this many times
+    /// the distance between their centers (exploded view); `0.0` keeps the pieces assembled.
+    pub explode: f64,
+}
+
+impl Default for MeshOptions {
+    fn default() -> MeshOptions {
+        MeshOptions {
+            cell_size: 1.0,
+            explode: 0.0,
+        }
+    }
+}
+
+impl MeshOptions {
+    pub fn new() -> MeshOptions {
+        MeshOptions::default()
+    }
+}
+
+/// A rectangular face given by its vertices in the counterclockwise order and its outward normal.
+///
+/// The vertices are the 4 corners of the rectangle and the corners of other faces lying on its edges.
+struct Face {
+    vertices: Vec<[f64; 3]>,
+    normal: [f64; 3],
+}
+
+/// A group of faces exported as a separate object.
+struct Object {
+    name: String,
+    faces: Vec<Face>,
+}
+
+/// Returns the faces of the boundary of `cells`, moved by `offset` and scaled by `cell_size`.
+fn boundary_faces(cells: &[Coord], offset: [f64; 3], cell_size: f64) -> Vec<Face> {
+    let set = cells.iter().map(|cd| [cd.x, cd.y, cd.z]).collect::<BTreeSet<_>>();
+    // rectangles as their corners in the counterclockwise order and the direction of their normals
+    let mut rects: Vec<([[i32; 3]; 4], usize, i32)> = vec![];
+
+    for axis in 0..3 {
+        // `(axis, u, v)` is a right-handed system
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        for &sign in &[1, -1] {
+            // unit squares facing the direction, as (plane, u, v)
+            let mut squares = BTreeSet::new();
+            for c in &set {
+                let mut nb = *c;
+                nb[axis] += sign;
+                if !set.contains(&nb) {
+                    squares.insert((c[axis] + if sign > 0 { 1 } else { 0 }, c[u_axis], c[v_axis]));
+                }
+            }
+
+            // merge the squares greedily into rectangles
+            while let Some(&(plane, u, v)) = squares.iter().next() {
+                let mut width = 1;
+                while squares.contains(&(plane, u + width, v)) {
+                    width += 1;
+                }
+                let mut height = 1;
+                while (0..width).all(|i| squares.contains(&(plane, u + i, v + height))) {
+                    height += 1;
+                }
+                for j in 0..height {
+                    for i in 0..width {
+                        squares.remove(&(plane, u + i, v + j));
+                    }
+                }
+
+                let point = |pu: i32, pv: i32| {
+                    let mut p = [0; 3];
+                    p[axis] = plane;
+                    p[u_axis] = pu;
+                    p[v_axis] = pv;
+                    p
+                };
+                let mut corners = [point(u, v), point(u + width, v), point(u + width, v + height), point(u, v + height)];
+                if sign < 0 {
+                    corners.reverse();
+                }
+                rects.push((corners, axis, sign));
+            }
+        }
+    }
+
+    let all_corners = rects.iter().flat_map(|r| r.0.iter().cloned()).collect::<BTreeSet<_>>();
+    let scale = |p: [i32; 3]| {
+        [(p[0] as f64 + offset[0]) * cell_size, (p[1] as f64 + offset[1]) * cell_size, (p[2] as f64 + offset[2]) * cell_size]
+    };
+    rects.into_iter().map(|(corners, axis, sign)| {
+        let mut points = vec![];
+        for i in 0..4 {
+            let (a, b) = (corners[i], corners[(i + 1) % 4]);
+            points.push(scale(a));
+            // the vertices strictly inside the edge from `a` to `b`, in the order from `a`
+            let d = (0..3).find(|&d| a[d] != b[d]).unwrap();
+            let mut inner = all_corners.iter()
+                .filter(|p| (0..3).all(|e| e == d || p[e] == a[e]) && a[d].min(b[d]) < p[d] && p[d] < a[d].max(b[d]))
+                .cloned()
+                .collect::<Vec<_>>();
+            inner.sort_by_key(|p| (p[d] - a[d]).abs());
+            points.extend(inner.into_iter().map(scale));
+        }
+        let mut normal = [0.0; 3];
+        normal[axis] = sign as f64;
+        Face { vertices: points, normal }
+    }).collect()
+}
+
+fn shape_objects(shape: &Shape, options: &MeshOptions) -> Vec<Object> {
+    let cells = shape.size().into_iter().filter(|&cd| shape.get(cd)).collect::<Vec<_>>();
+    vec![Object { name: "shape".to_string(), faces: boundary_faces(&cells, [0.0; 3], options.cell_size) }]
+}
+
+/// Returns an object for each piece instance of `answer`; blocked cells are not exported.
+fn answer_objects(answer: &Answer, options: &MeshOptions) -> Vec<Object> {
+    let size = answer.size();
+    let mut instances = vec![];
+    for cd in size {
+        let (piece, copy) = answer[cd];
+        if piece < 0 { continue; }
+        match instances.iter_mut().find(|inst: &&mut ((i32, i32), Vec<Coord>)| inst.0 == (piece, copy)) {
+            Some(inst) => inst.1.push(cd),
+            None => instances.push(((piece, copy), vec![cd])),
+        }
+    }
+    instances.sort();
+
+    let center = [size.x as f64 / 2.0, size.y as f64 / 2.0, size.z as f64 / 2.0];
+    instances.into_iter().map(|((piece, copy), cells)| {
+        let n = cells.len() as f64;
+        let inst_center = [
+            cells.iter().map(|cd| cd.x as f64 + 0.5).sum::<f64>() / n,
+            cells.iter().map(|cd| cd.y as f64 + 0.5).sum::<f64>() / n,
+            cells.iter().map(|cd| cd.z as f64 + 0.5).sum::<f64>() / n,
+        ];
+        let mut offset = [0.0; 3];
+        for i in 0..3 {
+            offset[i] = options.explode * (inst_center[i] - center[i]);
+        }
+        Object { name: format!("piece{}_{}", piece, copy), faces: boundary_faces(&cells, offset, options.cell_size) }
+    }).collect()
+}
+
+fn write_obj(objects: &[Object]) -> String {
+    let mut ret = String::new();
+    let mut n_vertices = 0;
+    for obj in objects {
+        let _ = writeln!(ret, "o {}", obj.name);
+        let mut vertices: Vec<[f64; 3]> = vec![];
+        let mut faces = vec![];
+        for face in &obj.faces {
+            let mut ids = vec![];
+            for p in &face.vertices {
+                let id = match vertices.iter().position(|q| q == p) {
+                    Some(id) => id,
+                    None => {
+                        vertices.push(*p);
+                        vertices.len() - 1
+                    }
+                };
+                ids.push(n_vertices + id + 1);
+            }
+            faces.push(ids);
+        }
+        for v in &vertices {
+            let _ = writeln!(ret, "v {} {} {}", v[0], v[1], v[2]);
+        }
+        for ids in &faces {
+            let ids = ids.iter().map(|id: &usize| id.to_string()).collect::<Vec<_>>();
+            let _ = writeln!(ret, "f {}", ids.join(" "));
+        }
+        n_vertices += vertices.len();
+    }
+    ret
+}
+
+/// Splits a face into triangles: two if it has only its 4 corners, or else a fan around its center,
+/// which keeps the vertices on its edges from making degenerate triangles.
+fn triangles(face: &Face) -> Vec<[[f64; 3]; 3]> {
+    let c = &face.vertices;
+    if c.len() == 4 {
+        return vec![[c[0], c[1], c[2]], [c[0], c[2], c[3]]];
+    }
+    let n = c.len() as f64;
+    let mut center = [0.0; 3];
+    for i in 0..3 {
+        center[i] = c.iter().map(|p| p[i]).sum::<f64>() / n;
+    }
+    (0..c.len()).map(|i| [center, c[i], c[(i + 1) % c.len()]]).collect()
+}
+
+fn write_ascii_stl(objects: &[Object]) -> String {
+    let mut ret = String::new();
+    for obj in objects {
+        let _ = writeln!(ret, "solid {}", obj.name);
+        for face in &obj.faces {
+            for tri in &triangles(face) {
+                let n = face.normal;
+                let _ = writeln!(ret, "  facet normal {} {} {}", n[0], n[1], n[2]);
+                ret.push_str("    outer loop\n");
+                for v in tri {
+                    let _ = writeln!(ret, "      vertex {} {} {}", v[0], v[1], v[2]);
+                }
+                ret.push_str("    endloop\n  endfacet\n");
+            }
+        }
+        let _ = writeln!(ret, "endsolid {}", obj.name);
+    }
+    ret
+}
+
+fn write_binary_stl(objects: &[Object]) -> Vec<u8> {
+    let mut ret = vec![0u8; 80];
+    let n_triangles = objects.iter().flat_map(|obj| &obj.faces).map(|face| triangles(face).len()).sum::<usize>();
+    ret.extend_from_slice(&(n_triangles as u32).to_le_bytes());
+    for obj in objects {
+        for face in &obj.faces {
+            for tri in &triangles(face) {
+                for &x in face.normal.iter().chain(tri.iter().flatten()) {
+                    ret.extend_from_slice(&(x as f32).to_le_bytes());
+                }
+                ret.extend_from_slice(&[0, 0]);
+            }
+        }
+    }
+    ret
+}
+
+impl Shape {
+    /// Returns the shape as a Wavefront OBJ model with a single object.
+    pub fn to_obj(&self, options: &MeshOptions) -> String {
+        write_obj(&shape_objects(self, options))
+    }
+    /// Returns the shape as an ASCII STL model.
+    pub fn to_stl(&self, options: &MeshOptions) -> String {
+        write_ascii_stl(&shape_objects(self, options))
+    }
+    /// Returns the shape as a binary STL model.
+    pub fn to_binary_stl(&self, options: &MeshOptions) -> Vec<u8> {
+        write_binary_stl(&shape_objects(self, options))
+    }
+}
+
+impl Answer {
+    /// Returns the answer as a Wavefront OBJ model with an object `piece<piece>_<copy>` for each piece instance.
+    pub fn to_obj(&self, options: &MeshOptions) -> String {
+        write_obj(&answer_objects(self, options))
+    }
+    /// Returns the answer as an ASCII STL model with a solid `piece<piece>_<copy>` for each piece instance.
+    pub fn to_stl(&self, options: &MeshOptions) -> String {
+        write_ascii_stl(&answer_objects(self, options))
+    }
+    /// Returns the answer as a binary STL model. The format has no groups, so the pieces are told apart only by
+    /// being separate closed surfaces.
+    pub fn to_binary_stl(&self, options: &MeshOptions) -> Vec<u8> {
+        write_binary_stl(&answer_objects(self, options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    /// Returns the volume enclosed by `faces`, which is negative if they are wound the wrong way.
+    fn volume(faces: &[Face]) -> f64 {
+        let mut ret = 0.0;
+        for face in faces {
+            for [a, b, c] in triangles(face) {
+                ret += (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0]) + a[2] * (b[0] * c[1] - b[1] * c[0])) / 6.0;
+            }
+        }
+        ret
+    }
+
+    /// Asserts that each edge of the triangles of `faces` is shared by exactly two triangles, in opposite directions.
+    fn assert_closed(faces: &[Face]) {
+        let key = |p: [f64; 3]| p.map(f64::to_bits);
+        let mut edges = BTreeMap::new();
+        for face in faces {
+            for tri in triangles(face) {
+                for i in 0..3 {
+                    *edges.entry((key(tri[i]), key(tri[(i + 1) % 3]))).or_insert(0) += 1;
+                }
+            }
+        }
+        for (&(a, b), &n) in &edges {
+            assert_eq!(n, 1);
+            assert_eq!(edges.get(&(b, a)), Some(&1));
+        }
+    }
+
+    #[test]
+    fn test_mesh() {
+        let options = MeshOptions::new();
+        let cube = Shape::filled(Coord { x: 1, y: 1, z: 1 });
+        let obj = cube.to_obj(&options);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 6);
+
+        // coplanar faces are merged
+        let bar = Shape::filled(Coord { x: 3, y: 2, z: 1 });
+        let obj = bar.to_obj(&options);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 6);
+        assert_eq!(bar.to_stl(&options).matches("facet normal").count(), 12);
+        assert_eq!(bar.to_binary_stl(&options).len(), 84 + 50 * 12);
+
+        let mut options = MeshOptions::new();
+        options.cell_size = 2.0;
+        let tetracube = library::tetracubes().into_iter().map(|p| p.1).find(|s| s.size().z == 2).unwrap();
+        let objects = shape_objects(&tetracube, &options);
+        assert!((volume(&objects[0].faces) - 32.0).abs() < 1e-9);
+
+        // the surfaces are closed even where rectangles meet at corners of other rectangles
+        for (_, shape, _) in library::tetracubes().into_iter().chain(library::hexominoes()) {
+            let objects = shape_objects(&shape, &options);
+            assert_closed(&objects[0].faces);
+            assert!((volume(&objects[0].faces) - 8.0 * shape.volume() as f64).abs() < 1e-9);
+        }
+        let l_tetromino = Shape::from_grid(&["###", "#.."]).unwrap();
+        let faces = &shape_objects(&l_tetromino, &MeshOptions::new())[0].faces;
+        assert_eq!(faces.iter().filter(|f| f.vertices.len() > 4).count(), 3);
+        assert_eq!(l_tetromino.to_stl(&MeshOptions::new()).matches("facet normal").count(), faces.iter().map(|f| triangles(f).len()).sum::<usize>());
+
+        // each piece instance is a separate object, moved apart in the exploded view
+        let mut answer = Answer::new(Coord { x: 2, y: 2, z: 1 });
+        answer[Coord { x: 0, y: 0, z: 0 }] = (0, 0);
+        answer[Coord { x: 1, y: 0, z: 0 }] = (0, 0);
+        answer[Coord { x: 0, y: 1, z: 0 }] = (0, 1);
+        answer[Coord { x: 1, y: 1, z: 0 }] = BLOCKED;
+        options.explode = 1.0;
+        let objects = answer_objects(&answer, &options);
+        assert_eq!(objects.iter().map(|obj| &obj.name[..]).collect::<Vec<_>>(), vec!["piece0_0", "piece0_1"]);
+        assert!((volume(&objects[0].faces) - 16.0).abs() < 1e-9);
+        assert!((volume(&objects[1].faces) - 8.0).abs() < 1e-9);
+        assert_eq!(objects[1].faces.iter().map(|f| f.vertices[0][0]).fold(f64::INFINITY, f64::min), -1.0);
+        let obj = answer.to_obj(&options);
+        assert!(obj.contains("o piece0_1\n"));
+        assert!(obj.contains("f 9 "));
+    }
+}
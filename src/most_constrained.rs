@@ -0,0 +1,265 @@
+use super::*;
+
+/// A candidate placement of a piece, together with where it is stored in `Dictionary::placements`.
+#[derive(Clone)]
+struct Candidate {
+    piece: usize,
+    handle: i32,
+    ori: i32,
+    cells: Vec<i32>,
+}
+
+/// Depth-first search always filling the empty cell with the fewest live placements.
+///
+/// A placement is live if it does not overlap the pieces placed so far and its piece is still available.
+/// The number of live placements covering each cell is maintained incrementally:
+/// `kill_count` counts the reasons why a placement is not live (overlapping pieces and the exhaustion of its piece),
+/// and `live_count` of each of its cells is decremented when it becomes positive.
+#[derive(Clone)]
+pub struct MostConstrainedSearch<'a, T: Bits + 'a> {
+    dic: &'a Dictionary<T>,
+    placements: Vec<Candidate>,
+    cover: Vec<Vec<usize>>, // cell -> placements covering it
+    by_piece: Vec<Vec<usize>>, // piece -> placements of it
+    placement_id: Vec<Vec<Vec<usize>>>, // handle, piece, orientation -> placement
+
+    kill_count: Vec<i32>,
+    live_count: Vec<i32>,
+    filled: Vec<bool>,
+    rem_piece: Vec<i32>,
+    answer_raw: Vec<(i32, i32)>,
+}
+
+impl<'a, T: Bits> MostConstrainedSearch<'a, T> {
+    pub fn new(dic: &'a Dictionary<T>) -> MostConstrainedSearch<'a, T> {
+        let n_cells = dic.n_target_cells as usize;
+        let n_pieces = dic.piece_count.len();
+
+        let mut placements = vec![];
+        let mut cover = vec![vec![]; n_cells];
+        let mut by_piece = vec![vec![]; n_pieces];
+        let mut placement_id = vec![vec![vec![]; n_pieces]; n_cells];
+
+        for (c, cell_placements) in dic.placements.iter().enumerate() {
+            for (p, piece_placements) in cell_placements.iter().enumerate() {
+                for (ori, m) in piece_placements.iter().enumerate() {
+                    let id = placements.len();
+                    let mut cells = vec![];
+                    let mut m = m.clone();
+                    while !m.is_empty() {
+                        let idx = m.lowest_set_bit();
+                        m.unset(idx);
+                        cells.push(idx);
+                        cover[idx as usize].push(id);
+                    }
+                    by_piece[p].push(id);
+                    placement_id[c][p].push(id);
+                    placements.push(Candidate {
+                        piece: p,
+                        handle: c as i32,
+                        ori: ori as i32,
+                        cells,
+                    });
+                }
+            }
+        }
+
+        let mut ret = MostConstrainedSearch {
+            dic,
+            kill_count: vec![0; placements.len()],
+            live_count: cover.iter().map(|v| v.len() as i32).collect(),
+            placements,
+            cover,
+            by_piece,
+            placement_id,
+            filled: vec![false; n_cells],
+            rem_piece: dic.piece_count.clone(),
+            answer_raw: vec![(-1, -1); n_cells],
+        };
+        for p in 0..n_pieces {
+            if ret.rem_piece[p] == 0 {
+                ret.kill_piece(p);
+            }
+        }
+        ret
+    }
+
+    /// Enumerates all answers starting from the `initial`-th initial placement of `Dictionary`.
+    pub fn search_initial(&mut self, initial: usize, ctx: &mut SearchContext) {
+        let dic = self.dic;
+        let initial_ids = dic.initial_placement_id[initial].iter()
+            .map(|&(cell, piece, ori)| self.placement_id[cell as usize][piece as usize][ori as usize])
+            .collect::<Vec<_>>();
+
+        for &id in &initial_ids {
+            self.place(id);
+        }
+        // withhold the copies of the special pieces not placed
+        let mut withheld = vec![];
+        for p in 0..self.rem_piece.len() {
+            while self.rem_piece[p] > dic.initial_piece_count[initial][p] {
+                self.withhold_piece(p);
+                withheld.push(p);
+            }
+        }
+        ctx.symmetry = dic.initial_symmetry[initial];
+        self.search(ctx);
+        for &p in withheld.iter().rev() {
+            self.release_piece(p);
+        }
+        for &id in initial_ids.iter().rev() {
+            self.unplace(id);
+        }
+    }
+
+    fn search(&mut self, ctx: &mut SearchContext) {
+        // find the most constrained cell
+        let mut pos = None;
+        let mut pos_count = i32::MAX;
+        for c in 0..self.filled.len() {
+            if !self.filled[c] && self.live_count[c] < pos_count {
+                pos = Some(c);
+                pos_count = self.live_count[c];
+            }
+        }
+
+        let pos = match pos {
+            Some(pos) => pos,
+            None => {
+                let dic = self.dic;
+                save_answer(dic, &self.answer_raw, ctx);
+                return;
+            }
+        };
+        if pos_count == 0 { return; }
+
+        for k in 0..self.cover[pos].len() {
+            let id = self.cover[pos][k];
+            if self.kill_count[id] > 0 { continue; }
+
+            if ctx.step() { break; }
+            self.place(id);
+            self.search(ctx);
+            self.unplace(id);
+            if ctx.terminated { break; }
+        }
+    }
+
+    fn place(&mut self, id: usize) {
+        for k in 0..self.placements[id].cells.len() {
+            let c = self.placements[id].cells[k] as usize;
+            for l in 0..self.cover[c].len() {
+                let other = self.cover[c][l];
+                self.kill(other);
+            }
+            self.filled[c] = true;
+        }
+
+        let Candidate { piece, handle, ori, .. } = self.placements[id];
+        self.answer_raw[handle as usize] = (piece as i32, ori);
+        self.rem_piece[piece] -= 1;
+        if self.rem_piece[piece] == 0 {
+            self.kill_piece(piece);
+        }
+    }
+
+    /// Undo `place`
+    fn unplace(&mut self, id: usize) {
+        let Candidate { piece, handle, .. } = self.placements[id];
+        if self.rem_piece[piece] == 0 {
+            self.revive_piece(piece);
+        }
+        self.rem_piece[piece] += 1;
+        self.answer_raw[handle as usize] = (-1, -1);
+
+        for k in (0..self.placements[id].cells.len()).rev() {
+            let c = self.placements[id].cells[k] as usize;
+            self.filled[c] = false;
+            for l in (0..self.cover[c].len()).rev() {
+                let other = self.cover[c][l];
+                self.revive(other);
+            }
+        }
+    }
+
+    /// Makes a copy of `piece` unavailable without placing it.
+    fn withhold_piece(&mut self, piece: usize) {
+        self.rem_piece[piece] -= 1;
+        if self.rem_piece[piece] == 0 {
+            self.kill_piece(piece);
+        }
+    }
+
+    /// Undo `withhold_piece`
+    fn release_piece(&mut self, piece: usize) {
+        if self.rem_piece[piece] == 0 {
+            self.revive_piece(piece);
+        }
+        self.rem_piece[piece] += 1;
+    }
+
+    fn kill_piece(&mut self, piece: usize) {
+        for k in 0..self.by_piece[piece].len() {
+            let id = self.by_piece[piece][k];
+            self.kill(id);
+        }
+    }
+
+    /// Undo `kill_piece`
+    fn revive_piece(&mut self, piece: usize) {
+        for k in (0..self.by_piece[piece].len()).rev() {
+            let id = self.by_piece[piece][k];
+            self.revive(id);
+        }
+    }
+
+    fn kill(&mut self, id: usize) {
+        self.kill_count[id] += 1;
+        if self.kill_count[id] == 1 {
+            for &c in &self.placements[id].cells {
+                self.live_count[c as usize] -= 1;
+            }
+        }
+    }
+
+    /// Undo `kill`
+    fn revive(&mut self, id: usize) {
+        self.kill_count[id] -= 1;
+        if self.kill_count[id] == 0 {
+            for &c in &self.placements[id].cells {
+                self.live_count[c as usize] += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve_by(problem: &Puzzle, backend: Backend) -> Answers {
+        let mut options = SolveOptions::new();
+        options.backend = backend;
+        let mut answers = solve_with_options(problem, &options).unwrap();
+        answers.answer.sort();
+        answers
+    }
+
+    #[test]
+    fn test_most_constrained_answers() {
+        let l_tromino = Shape::from_grid(&["#.", "##"]).unwrap();
+        let problems = vec![
+            (Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 5, y: 4, z: 1 })), 50),
+            (Puzzle::new(vec![(l_tromino, 8)], Shape::filled(Coord { x: 4, y: 3, z: 2 })), 621),
+            (Puzzle::with_names(library::soma_pieces(), Shape::filled(Coord { x: 3, y: 3, z: 3 })), 240),
+        ];
+        for &(ref problem, n_answers) in &problems {
+            let answers = solve_by(problem, Backend::MostConstrained);
+            let expected = solve_by(problem, Backend::Bitmask);
+
+            assert_eq!(answers.count, n_answers);
+            assert_eq!(expected.count, n_answers);
+            assert!(answers.answer == expected.answer);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The algorithm used for the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Choose one of the following by `choose_backend`.
+    Auto,
+    /// Depth-first search on bitmasks, always filling the lowest empty cell.
+    Bitmask,
+    /// Depth-first search always filling the empty cell with the fewest placements still available.
+    /// Each step is more expensive than that of `Bitmask`, but the search tree is much smaller
+    /// for irregular targets such as those with holes or narrow corridors.
+    MostConstrained,
+    /// Dancing Links (`ExactCover`), always covering the column with the fewest candidates.
+    DancingLinks,
+}
+
+/// What the search looks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    /// Tilings covering every cell of the target.
+    ExactCover,
+    /// Packings with the largest number of pieces, where cells of the target may be left empty.
+    MaxPieces,
+    /// Packings covering the largest number of cells of the target.
+    MaxVolume,
+}
+
+/// Options controlling a search.
+#[derive(Clone)]
+pub struct SolveOptions {
+    /// The search is interrupted once this point in time is passed.
+    pub deadline: Option<Instant>,
+    /// The search is interrupted once `Answers::search_steps` reaches this value.
+    pub max_search_steps: Option<u64>,
+    /// The search is interrupted once this flag is set to `true` (possibly from another thread).
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// The number of threads used for the search (`0` stands for the number of available cores).
+    pub threads: usize,
+    /// The algorithm used for the search (`Backend::Auto` by default).
+    pub backend: Backend,
+    /// Packing objectives are searched by a dedicated single-threaded search, ignoring `threads` and `backend`.
+    pub objective: Objective,
+    /// Also count the answers equivalent to the ones found under the symmetries of the target
+    /// (`Answers::raw_count` and `Answers::orbit_size`), which takes extra time for each answer.
+    pub count_symmetric: bool,
+}
+
+impl Default for SolveOptions {
+    fn default() -> SolveOptions {
+        SolveOptions {
+            deadline: None,
+            max_search_steps: None,
+            cancel: None,
+            threads: 1,
+            backend: Backend::Auto,
+            objective: Objective::ExactCover,
+            count_symmetric: false,
+        }
+    }
+}
+
+impl SolveOptions {
+    pub fn new() -> SolveOptions {
+        SolveOptions::default()
+    }
+    /// Sets `deadline` to `limit` from now.
+    pub fn time_limit(mut self, limit: Duration) -> SolveOptions {
+        self.deadline = Some(Instant::now() + limit);
+        self
+    }
+    pub fn n_threads(&self) -> usize {
+        if self.threads == 0 {
+            thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+        } else {
+            self.threads
+        }
+    }
+}
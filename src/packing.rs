@@ -0,0 +1,191 @@
+use super::*;
+
+/// Branch-and-bound search for packings, where cells of the target may be left empty.
+///
+/// Cells are decided in the order of cell ids: the lowest undecided cell is either covered by a placement
+/// whose lowest cell it is, or left empty. Each packing is thus reached in exactly one way.
+/// The first pass finds the optimal value, and the second pass enumerates the packings achieving it.
+struct PackingSearch<'a, T: Bits + 'a> {
+    dic: &'a Dictionary<T>,
+    objective: Objective,
+    /// Pieces in the ascending order of volumes, for bounding the number of pieces.
+    by_volume: Vec<usize>,
+    /// The best value found so far in the first pass, or the optimal value in the second pass.
+    best: i32,
+    enumerate: bool,
+
+    rem_piece: Vec<i32>,
+    answer_raw: Vec<(i32, i32)>,
+    /// Cells covered or left empty.
+    decided: T,
+}
+
+impl<'a, T: Bits> PackingSearch<'a, T> {
+    fn new(dic: &'a Dictionary<T>, objective: Objective) -> PackingSearch<'a, T> {
+        let mut by_volume = (0..dic.piece_count.len()).collect::<Vec<_>>();
+        by_volume.sort_by_key(|&i| dic.piece_volume[i]);
+        PackingSearch {
+            dic,
+            objective,
+            by_volume,
+            best: -1,
+            enumerate: false,
+            rem_piece: dic.piece_count.clone(),
+            answer_raw: vec![(-1, -1); dic.n_target_cells as usize],
+            decided: dic.empty_mask(),
+        }
+    }
+
+    /// Returns an upper bound of the value which can be added by covering some of `free` undecided cells.
+    fn bound(&self, free: i32) -> i32 {
+        match self.objective {
+            Objective::MaxVolume => {
+                let mut volume = 0;
+                for i in 0..self.rem_piece.len() {
+                    volume += self.rem_piece[i] * self.dic.piece_volume[i];
+                    if volume >= free {
+                        return free;
+                    }
+                }
+                volume
+            }
+            _ => {
+                // the smallest pieces are the most that fit
+                let mut ret = 0;
+                let mut free = free;
+                for &i in &self.by_volume {
+                    let vol = self.dic.piece_volume[i];
+                    let n = std::cmp::min(self.rem_piece[i], free / vol);
+                    ret += n;
+                    free -= n * vol;
+                    if n < self.rem_piece[i] { break; }
+                }
+                ret
+            }
+        }
+    }
+
+    fn search(&mut self, value: i32, free: i32, ctx: &mut SearchContext) {
+        let bound = value + self.bound(free);
+        if bound < self.best || (!self.enumerate && bound == self.best) {
+            return;
+        }
+
+        let pos = self.decided.lowest_unset_bit();
+        if pos == self.dic.n_target_cells {
+            if self.enumerate {
+                save_answer(self.dic, &self.answer_raw, ctx);
+            } else {
+                self.best = value;
+            }
+            return;
+        }
+
+        let dic = self.dic;
+        for i in 0..self.rem_piece.len() {
+            if self.rem_piece[i] == 0 { continue; }
+            let vol = dic.piece_volume[i];
+            let gain = if self.objective == Objective::MaxVolume { vol } else { 1 };
+            self.rem_piece[i] -= 1;
+            for j in 0..dic.placements[pos as usize][i].len() {
+                let m = &dic.placements[pos as usize][i][j];
+                if ctx.step() { break; }
+                if self.decided.disjoint(m) {
+                    self.decided.update(m);
+                    self.answer_raw[pos as usize] = (i as i32, j as i32);
+                    self.search(value + gain, free - vol, ctx);
+                    self.decided.update(m);
+                    if ctx.terminated { break; }
+                }
+            }
+            self.rem_piece[i] += 1;
+            if ctx.terminated { break; }
+        }
+        self.answer_raw[pos as usize] = (-1, -1);
+
+        if !ctx.terminated {
+            // leave the cell empty
+            self.decided.set(pos);
+            self.search(value, free - 1, ctx);
+            self.decided.unset(pos);
+        }
+    }
+}
+
+/// Finds the packings of `dic` optimal with respect to `options.objective`, which must not be `Objective::ExactCover`.
+///
+/// The search runs on a single thread regardless of `options.threads` and `options.backend`.
+/// If it is interrupted while looking for the optimal value, no answers are reported and
+/// `Answers::optimum` is the best value found so far (if any).
+pub fn solve_packing<T: Bits>(dic: &Dictionary<T>, options: &SolveOptions, callback: &AnswerCallback) -> Answers {
+    let shared = SharedState::new();
+    let mut ctx = SearchContext::new(options, &shared, callback);
+    ctx.symmetry = dic.target_symmetry;
+
+    let mut search = PackingSearch::new(dic, options.objective);
+    search.search(0, dic.n_target_cells, &mut ctx);
+    if !ctx.terminated {
+        search.enumerate = true;
+        search.search(0, dic.n_target_cells, &mut ctx);
+    }
+    ctx.check_interrupt();
+
+    // fixed pieces count as well
+    let mut preset_value = 0;
+    for i in 0..dic.preset_count.len() {
+        preset_value += dic.preset_count[i] * if options.objective == Objective::MaxVolume { dic.piece_volume[i] } else { 1 };
+    }
+
+    let mut answers = ctx.answers;
+    if search.best >= 0 {
+        answers.optimum = Some(search.best + preset_value);
+    }
+    answers.status = shared.status();
+    answers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(problem: &Puzzle, objective: Objective) -> Answers {
+        let mut options = SolveOptions::new();
+        options.objective = objective;
+        solve_with_options(problem, &options).unwrap()
+    }
+
+    #[test]
+    fn test_packing() {
+        let l_tetromino = Shape::from_grid(&["###", "#.."]).unwrap();
+        let problem = Puzzle::new(vec![(l_tetromino.clone(), 100)], Shape::filled(Coord { x: 5, y: 5, z: 1 }));
+        assert!(solve(&problem).is_ok());
+
+        let answers = pack(&problem, Objective::MaxPieces);
+        assert_eq!(answers.optimum, Some(6));
+        assert_eq!(answers.count, 18);
+        for a in &answers.answer {
+            assert_eq!(Coord { x: 5, y: 5, z: 1 }.into_iter().filter(|&cd| a[cd] == UNFILLED).count(), 1);
+        }
+
+        let problem = Puzzle::new(vec![(Shape::from_grid(&["##", "#."]).unwrap(), 100)], Shape::filled(Coord { x: 5, y: 5, z: 1 }));
+        let answers = pack(&problem, Objective::MaxVolume);
+        assert_eq!(answers.optimum, Some(24));
+        assert_eq!(answers.count, 18);
+
+        // fewer pieces than needed for a tiling
+        let problem = Puzzle::new(vec![(l_tetromino.clone(), 1), (Shape::filled(Coord { x: 2, y: 1, z: 1 }), 1)], Shape::filled(Coord { x: 3, y: 3, z: 1 }));
+        assert_eq!(solve(&problem).err(), Some(PolymateError::VolumeMismatch { pieces: 6, target: 9 }));
+        assert_eq!(pack(&problem, Objective::MaxPieces).optimum, Some(2));
+        assert_eq!(pack(&problem, Objective::MaxVolume).optimum, Some(6));
+
+        // fixed pieces count, and blocked cells stay blocked
+        let mut problem = Puzzle::new(vec![(l_tetromino, 3)], Shape::filled(Coord { x: 3, y: 3, z: 1 }));
+        problem.blocked.push(Coord { x: 1, y: 1, z: 0 });
+        problem.fixed.push(FixedPlacement { piece: 0, transformation: Transformation::id(), offset: Coord { x: 0, y: 0, z: 0 } });
+        let answers = pack(&problem, Objective::MaxPieces);
+        assert_eq!(answers.optimum, Some(2));
+        assert_eq!(answers.count, 1);
+        assert_eq!(answers.answer[0][Coord { x: 1, y: 1, z: 0 }], BLOCKED);
+        assert_eq!(answers.answer[0][Coord { x: 2, y: 2, z: 0 }].0, 0);
+    }
+}
@@ -71,7 +71,7 @@ impl Iterator for CoordIterator {
     }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct Shape {
     size: Coord,
     data: Vec<bool>,
@@ -90,9 +90,13 @@ impl Shape {
             data: vec![true; (size.x * size.y * size.z) as usize],
         }
     }
-    pub fn from_grid(grid: &[&'static str]) -> Shape {
-        let width = grid[0].len() as i32;
+    /// Builds a planar shape from rows of `#` (filled) and other characters (empty).
+    pub fn from_grid(grid: &[&str]) -> Result<Shape, PolymateError> {
+        let width = grid.first().map_or(0, |row| row.chars().count()) as i32;
         let height = grid.len() as i32;
+        if grid.iter().any(|row| row.chars().count() as i32 != width) {
+            return Err(PolymateError::RaggedGrid);
+        }
         let mut ret = Shape::new(Coord { x: width, y: height, z: 1 });
 
         for y in 0..height {
@@ -101,7 +105,7 @@ impl Shape {
                 ret.set(Coord { x, y, z: 0 }, it.next() == Some('#'));
             }
         }
-        ret
+        Ok(ret)
     }
     pub fn size(&self) -> Coord {
         self.size
@@ -145,36 +149,50 @@ impl Shape {
         }
         true
     }
-    pub fn get_piece_mask<T: Bits>(&self, piece: &Shape, offset: Coord) -> T {
+    pub fn get_piece_mask<T: Bits>(&self, piece: &Shape, offset: Coord) -> Result<T, PolymateError> {
         let piece_size = piece.size();
         let mut counter = 0;
-        let mut ret = T::allocate(self.volume());
+        let mut ret = T::allocate(self.volume())?;
         for cd in self.size {
             if self.get(cd) {
                 let piece_cd = cd - offset;
-                if 0 <= piece_cd.x && piece_cd.x < piece_size.x && 0 <= piece_cd.y && piece_cd.y < piece_size.y && 0 <= piece_cd.z && piece_cd.z < piece_size.z {
-                    if piece.get(piece_cd) {
-                        ret.set(counter);
-                    }
+                if 0 <= piece_cd.x && piece_cd.x < piece_size.x && 0 <= piece_cd.y && piece_cd.y < piece_size.y && 0 <= piece_cd.z && piece_cd.z < piece_size.z
+                    && piece.get(piece_cd) {
+                    ret.set(counter);
                 }
                 counter += 1;
             }
         }
-        ret
+        Ok(ret)
     }
     pub fn symmetry(&self) -> Symmetry {
         let mut ret = 1u64;
-        for i in 1..48 {
-            if *self == self.trans(TRANSFORMATIONS[i]) {
+        for (i, &t) in TRANSFORMATIONS.iter().enumerate().skip(1) {
+            if *self == self.trans(t) {
                 ret |= 1u64 << i;
             }
         }
         ret
     }
+    /// Returns the distinct shapes `self` can be turned into by rotations, in the order of `ROTATIONS`.
+    pub fn orientations(&self) -> Vec<Shape> {
+        self.orientations_under(&ROTATIONS)
+    }
+    /// Returns the distinct shapes `self` can be turned into by `transformations`, in the given order.
+    pub fn orientations_under(&self, transformations: &[Transformation]) -> Vec<Shape> {
+        let mut ret: Vec<Shape> = vec![];
+        for &rot in transformations {
+            let rotated = self.trans(rot);
+            if !ret.contains(&rotated) {
+                ret.push(rotated);
+            }
+        }
+        ret
+    }
     pub fn canonize(&self) -> Shape {
         let mut ret = self.clone();
-        for i in 1..24 {
-            let cand = self.trans(ROTATIONS[i]);
+        for &rot in &ROTATIONS[1..] {
+            let cand = self.trans(rot);
             if cand < ret {
                 ret = cand;
             }
@@ -183,7 +201,89 @@ impl Shape {
     }
 }
 
+/// The orientations in which a piece may be placed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrientationPolicy {
+    /// Any rotation in space, which can turn a planar piece over (the default).
+    Rotations,
+    /// Any rotation or reflection, so that a piece can also be placed as its mirror image.
+    Free,
+    /// Rotations within the xy-plane only, as for one-sided polyominoes.
+    InPlane,
+    /// Only the orientation in which the piece is given.
+    Fixed,
+    /// The given transformations only.
+    Explicit(Vec<Transformation>),
+}
+
+impl OrientationPolicy {
+    /// Returns the transformations allowed by `self`, starting with the identity if it is allowed.
+    pub fn transformations(&self) -> Vec<Transformation> {
+        match *self {
+            OrientationPolicy::Rotations => ROTATIONS.to_vec(),
+            OrientationPolicy::Free => TRANSFORMATIONS.to_vec(),
+            OrientationPolicy::InPlane => ROTATIONS.iter().cloned().filter(|t| t.is_in_plane()).collect(),
+            OrientationPolicy::Fixed => vec![Transformation::id()],
+            OrientationPolicy::Explicit(ref transformations) => transformations.clone(),
+        }
+    }
+}
+
+/// A copy of a piece placed in advance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedPlacement {
+    /// Index of the piece in `Puzzle::pieces`.
+    pub piece: usize,
+    pub transformation: Transformation,
+    /// Position of the transformed piece in the target.
+    pub offset: Coord,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Puzzle {
+    /// Pieces with the number of their copies.
     pub pieces: Vec<(Shape, i32)>,
     pub target: Shape,
+    /// Names of the pieces, parallel to `pieces`.
+    pub piece_names: Vec<String>,
+    /// Orientation policies of the pieces, parallel to `pieces`.
+    pub orientations: Vec<OrientationPolicy>,
+    /// Pieces placed in advance, which are taken from the copies in `pieces`.
+    pub fixed: Vec<FixedPlacement>,
+    /// Cells of the target which must not be covered.
+    pub blocked: Vec<Coord>,
+}
+
+impl Puzzle {
+    /// Creates a puzzle whose pieces are named `A`, `B`, ... in order.
+    pub fn new(pieces: Vec<(Shape, i32)>, target: Shape) -> Puzzle {
+        let piece_names = (0..pieces.len()).map(default_piece_name).collect();
+        let orientations = vec![OrientationPolicy::Rotations; pieces.len()];
+        Puzzle { pieces, target, piece_names, orientations, fixed: vec![], blocked: vec![] }
+    }
+
+    /// Creates a puzzle from named pieces.
+    pub fn with_names(pieces: Vec<(String, Shape, i32)>, target: Shape) -> Puzzle {
+        Puzzle::with_orientation(pieces, target, OrientationPolicy::Rotations)
+    }
+
+    /// Creates a puzzle from named pieces, all of which are placed under `orientation`.
+    pub fn with_orientation(pieces: Vec<(String, Shape, i32)>, target: Shape, orientation: OrientationPolicy) -> Puzzle {
+        let mut ret = Puzzle { pieces: vec![], target, piece_names: vec![], orientations: vec![], fixed: vec![], blocked: vec![] };
+        for (name, shape, count) in pieces {
+            ret.pieces.push((shape, count));
+            ret.piece_names.push(name);
+            ret.orientations.push(orientation.clone());
+        }
+        ret
+    }
+}
+
+/// `A` to `Z` for the first 26 pieces, and `P26`, `P27`, ... for the others.
+fn default_piece_name(i: usize) -> String {
+    if i < 26 {
+        ((b'A' + i as u8) as char).to_string()
+    } else {
+        format!("P{}", i)
+    }
 }
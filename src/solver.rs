@@ -1,114 +1,326 @@
 use super::*;
 
-// just counting # of answers
-pub fn solve(problem: &Puzzle) -> Answers {
-    let use_bitset = (problem.target.volume() > 64);
+use std::ops::ControlFlow;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Enumerates all answers of `problem` and stores them in `Answers::answer`.
+///
+/// Returns an error if `problem` is malformed (see `PolymateError`).
+pub fn solve(problem: &Puzzle) -> Result<Answers, PolymateError> {
+    solve_with_options(problem, &SolveOptions::new())
+}
+
+/// Same as `solve`, but the search may be cut short according to `options`.
+/// `Answers::status` tells whether the search was completed.
+///
+/// The order of `Answers::answer` does not depend on `SolveOptions::threads`.
+pub fn solve_with_options(problem: &Puzzle, options: &SolveOptions) -> Result<Answers, PolymateError> {
+    let answer = Mutex::new(vec![]);
+    let mut answers = solve_indexed(problem, options, &|task, a, orbit_size| {
+        answer.lock().unwrap().push((task, a.clone(), orbit_size));
+        ControlFlow::Continue(())
+    })?;
+
+    // answers of a task are pushed in order, so a stable sort restores the sequential order
+    let mut answer = answer.into_inner().unwrap();
+    answer.sort_by_key(|&(task, _, _)| task);
+    answers.orbit_size = answer.iter().filter_map(|&(_, _, orbit_size)| orbit_size).collect();
+    answers.answer = answer.into_iter().map(|(_, a, _)| a).collect();
+    Ok(answers)
+}
+
+/// Enumerates all answers of `problem`, handing each of them to `callback` as soon as it is found.
+///
+/// Answers are not buffered, so `Answers::answer` and `Answers::orbit_size` of the returned value are always empty.
+/// The search stops once `callback` returns `ControlFlow::Break`, and `callback` is never called again;
+/// the answer passed to that call is still included in `Answers::count`, while the answers other threads
+/// find afterwards are not.
+/// In a multithreaded search, `callback` is called from worker threads (one at a time)
+/// and answers are handed over in no particular order.
+pub fn solve_with<F>(problem: &Puzzle, options: &SolveOptions, callback: F) -> Result<Answers, PolymateError>
+    where F: FnMut(&Answer) -> ControlFlow<()> + Send
+{
+    // the number of answers delivered, and whether `callback` requested to stop
+    let state = Mutex::new((callback, 0u64, false));
+    let mut answers = solve_indexed(problem, options, &|_, a, _| {
+        let mut state = state.lock().unwrap();
+        if state.2 {
+            return ControlFlow::Break(());
+        }
+        state.1 += 1;
+        let ret = (state.0)(a);
+        state.2 = ret.is_break();
+        ret
+    })?;
+    let (_, n_delivered, stopped) = state.into_inner().unwrap();
+    if stopped {
+        answers.count = n_delivered;
+    }
+    Ok(answers)
+}
 
-    if use_bitset {
-        solve_typed::<Bitset>(problem)
+fn solve_indexed(problem: &Puzzle, options: &SolveOptions, callback: &AnswerCallback) -> Result<Answers, PolymateError> {
+    // the smallest fixed-width type holding all the cells is used
+    let volume = problem.target.volume();
+
+    if volume <= 64 {
+        solve_typed::<u64>(problem, options, callback)
+    } else if volume <= 128 {
+        solve_typed::<u128>(problem, options, callback)
+    } else if volume <= 192 {
+        solve_typed::<[u64; 3]>(problem, options, callback)
+    } else if volume <= 256 {
+        solve_typed::<[u64; 4]>(problem, options, callback)
     } else {
-        solve_typed::<u64>(problem)
+        solve_typed::<Bitset>(problem, options, callback)
     }
 }
 
-fn solve_typed<T: Bits + SearchHandler>(problem: &Puzzle) -> Answers {
-    let mut dic = Dictionary::<T>::new(problem);
+/// A subtree of the search, which is solved independently of the others.
+#[derive(Clone)]
+struct Task<T: Bits> {
+    initial: usize,
+    rem_piece: Vec<i32>,
+    answer_raw: Vec<(i32, i32)>,
+    mask: T,
+}
 
-    let mut answer_raw = vec![(-1, -1); dic.n_target_cells as usize];
-    let mut answers = Answers::new();
-    
-    for i in 0..dic.initial_piece_count.len() {
-        let mut rem_piece = dic.initial_piece_count[i].clone();
-        dic.target_symmetry = dic.initial_symmetry[i];
+/// Tasks are split until there are this many tasks per thread ...
+const TASKS_PER_THREAD: usize = 32;
+/// ... or this many pieces are placed in each task.
+const MAX_SPLIT_DEPTH: usize = 4;
 
+fn solve_typed<T: Bits + SearchHandler + Send + Sync>(problem: &Puzzle, options: &SolveOptions, callback: &AnswerCallback) -> Result<Answers, PolymateError> {
+    let dic = Dictionary::<T>::with_objective(problem, options.objective)?;
+    if options.objective != Objective::ExactCover {
+        return Ok(solve_packing(&dic, options, callback));
+    }
+
+    let answers = match options.backend {
+        Backend::Auto => match choose_backend(&dic) {
+            Backend::DancingLinks => solve_dancing_links(&dic, options, callback),
+            Backend::MostConstrained => solve_most_constrained(&dic, options, callback),
+            _ => solve_bitmask(&dic, options, callback),
+        },
+        Backend::Bitmask => solve_bitmask(&dic, options, callback),
+        Backend::MostConstrained => solve_most_constrained(&dic, options, callback),
+        Backend::DancingLinks => solve_dancing_links(&dic, options, callback),
+    };
+    Ok(answers)
+}
+
+/// Decides which backend `Backend::Auto` stands for.
+///
+/// The bitmask search is the fastest as long as the masks fit in a machine word,
+/// and it is also preferred when the puzzle has many copies of a few piece types
+/// or only a few placements cover each cell, where the lowest empty cell is about as constrained as any other.
+/// Otherwise the column heuristic of Dancing Links usually pays off.
+pub fn choose_backend<T: Bits>(dic: &Dictionary<T>) -> Backend {
+    if dic.n_target_cells <= 64 {
+        return Backend::Bitmask;
+    }
+
+    let mut n_types = 0;
+    let mut n_copies = 0;
+    let mut n_placements = 0;
+    for p in 0..dic.piece_count.len() {
+        if dic.piece_count[p] == 0 { continue; }
+        n_types += 1;
+        n_copies += dic.piece_count[p];
+        for c in 0..dic.placements.len() {
+            n_placements += dic.placements[c][p].len() as i32;
+        }
+    }
+
+    if n_copies >= 4 * n_types || n_placements < 8 * dic.n_target_cells {
+        Backend::Bitmask
+    } else {
+        Backend::DancingLinks
+    }
+}
+
+fn solve_bitmask<T: Bits + SearchHandler + Send + Sync>(dic: &Dictionary<T>, options: &SolveOptions, callback: &AnswerCallback) -> Answers {
+    let shared = SharedState::new();
+    let n_threads = options.n_threads();
+
+    let mut tasks = vec![];
+    for i in 0..dic.initial_piece_count.len() {
+        let mut answer_raw = vec![(-1, -1); dic.n_target_cells as usize];
         for &(cell, piece, ori) in &dic.initial_placement_id[i] {
             answer_raw[cell as usize] = (piece, ori);
         }
-        
-        T::search(&dic, &mut rem_piece, &mut answer_raw, dic.initial_placement[i].clone(), &mut answers);
+        tasks.push(Task {
+            initial: i,
+            rem_piece: dic.initial_piece_count[i].clone(),
+            answer_raw,
+            mask: dic.initial_placement[i].clone(),
+        });
+    }
 
-        for &(cell, piece, ori) in &dic.initial_placement_id[i] {
-            answer_raw[cell as usize] = (-1, -1);
+    // Splitting tasks takes the same search steps as the sequential search would,
+    // so the result does not depend on the number of threads.
+    let mut split_steps = 0;
+    if n_threads > 1 {
+        let mut ctx = SearchContext::new(options, &shared, callback);
+        let mut buf = RegionBuffer::new(dic);
+        let mut depth = 0;
+        while depth < MAX_SPLIT_DEPTH && tasks.len() < n_threads * TASKS_PER_THREAD && !ctx.terminated {
+            let mut next_tasks = vec![];
+            for task in &tasks {
+                split_task(dic, task, &mut next_tasks, &mut buf, &mut ctx);
+                if ctx.terminated { break; }
+            }
+            tasks = next_tasks;
+            depth += 1;
         }
+        ctx.check_interrupt();
+        split_steps = ctx.answers.search_steps;
     }
 
+    let mut answers = run_tasks(tasks.len(), options, &shared, callback, || (), |_, idx, ctx| {
+        let task = &tasks[idx];
+        ctx.symmetry = dic.initial_symmetry[task.initial];
+        T::search(dic, &mut task.rem_piece.clone(), &mut task.answer_raw.clone(), task.mask.clone(), ctx);
+    });
+    answers.search_steps += split_steps;
     answers
 }
 
-trait SearchHandler : Bits {
-    fn search(dic: &Dictionary<Self>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, mask_default: Self, answers: &mut Answers);
+fn solve_dancing_links<T: Bits + Send + Sync>(dic: &Dictionary<T>, options: &SolveOptions, callback: &AnswerCallback) -> Answers {
+    let shared = SharedState::new();
+    let exact_cover = ExactCover::from_dictionary(dic);
+
+    // each initial placement is a task
+    run_tasks(dic.initial_placement_id.len(), options, &shared, callback, || exact_cover.clone(), |exact_cover, idx, ctx| {
+        exact_cover.search_initial(idx, ctx);
+    })
 }
 
-impl SearchHandler for u64 {
-    fn search(dic: &Dictionary<Self>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, mask_default: Self, answers: &mut Answers) {
-        let mut is_zero_one = true;
-        for i in 0..rem_piece.len() {
-            if rem_piece[i] > 1 {
-                is_zero_one = false;
-                break;
-            }
+fn solve_most_constrained<T: Bits + Send + Sync>(dic: &Dictionary<T>, options: &SolveOptions, callback: &AnswerCallback) -> Answers {
+    let shared = SharedState::new();
+    let searcher = MostConstrainedSearch::new(dic);
+
+    // each initial placement is a task
+    run_tasks(dic.initial_placement_id.len(), options, &shared, callback, || searcher.clone(), |searcher, idx, ctx| {
+        searcher.search_initial(idx, ctx);
+    })
+}
+
+/// Runs tasks `0..n_tasks` on `options.n_threads()` threads and merges the results.
+///
+/// Each thread prepares its own working state by `init` and runs each task by `run`.
+fn run_tasks<S, I, R>(n_tasks: usize, options: &SolveOptions, shared: &SharedState, callback: &AnswerCallback, init: I, run: R) -> Answers
+    where I: Fn() -> S + Sync, R: Fn(&mut S, usize, &mut SearchContext) + Sync
+{
+    let n_threads = options.n_threads();
+    let next_task = AtomicUsize::new(0);
+    let run_worker = || {
+        let mut state = init();
+        let mut ctx = SearchContext::new(options, shared, callback);
+        ctx.check_interrupt();
+        while !ctx.terminated {
+            let idx = next_task.fetch_add(1, Ordering::Relaxed);
+            if idx >= n_tasks { break; }
+
+            ctx.task = idx;
+            run(&mut state, idx, &mut ctx);
+            if !ctx.terminated { ctx.check_interrupt(); }
         }
+        ctx.answers
+    };
 
-        if is_zero_one && rem_piece.len() <= 64 {
-            let mut rem_piece_bits = 0u64;
-            for i in 0..rem_piece.len() {
-                rem_piece_bits |= (rem_piece[i] as u64) << (i as u64);
-            }
-            search_with_u64_rem_piece(dic, rem_piece_bits, answer_raw, mask_default, answers);
-        } else {
-            search(dic, rem_piece, answer_raw, mask_default, answers);
+    let results = if n_threads > 1 {
+        thread::scope(|s| {
+            let handles = (0..n_threads).map(|_| s.spawn(run_worker)).collect::<Vec<_>>();
+            handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+        })
+    } else {
+        vec![run_worker()]
+    };
+
+    let mut answers = Answers::new();
+    for res in results {
+        answers.count += res.count;
+        answers.search_steps += res.search_steps;
+        if let Some(raw_count) = res.raw_count {
+            answers.raw_count = Some(answers.raw_count.unwrap_or(0) + raw_count);
         }
     }
+    answers.status = shared.status();
+    answers
 }
 
-impl SearchHandler for Bitset {
-    fn search(dic: &Dictionary<Self>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, mask_default: Self, answers: &mut Answers) {
-        let mut mask_default = mask_default;
-        search_generic(dic, rem_piece, answer_raw, &mut mask_default, answers);
+/// Places a piece on the lowest empty cell of `task` in every possible way, exactly as `search` does.
+fn split_task<T: Bits + SearchHandler>(dic: &Dictionary<T>, task: &Task<T>, next_tasks: &mut Vec<Task<T>>, buf: &mut RegionBuffer<T>, ctx: &mut SearchContext) {
+    let pos = task.mask.lowest_unset_bit();
+    if pos == dic.n_target_cells {
+        next_tasks.push(task.clone());
+        return;
+    }
+    if T::is_dead_end(dic, &task.mask) { return; }
+
+    for i in 0..task.rem_piece.len() {
+        if task.rem_piece[i] == 0 { continue; }
+        for j in 0..dic.placements[pos as usize][i].len() {
+            let m = &dic.placements[pos as usize][i][j];
+            if ctx.step() { return; }
+            if task.mask.disjoint(m) {
+                let mut child = task.clone();
+                child.rem_piece[i] -= 1;
+                child.mask.update(m);
+                if dic.region_pruning && has_dead_region(dic, &child.mask, m, buf, || reachable_volumes(dic, &child.rem_piece)) { continue; }
+                child.answer_raw[pos as usize] = (i as i32, j as i32);
+                next_tasks.push(child);
+            }
+        }
     }
 }
 
-#[derive(Clone, Copy)]
-struct PieceCountBitSeq {
-    val: u64
-}
-
-struct PieceCountBitSeqIter {
-    val: u64
-}
-
-impl IntoIterator for PieceCountBitSeq {
-    type Item = usize;
-    type IntoIter = PieceCountBitSeqIter;
+trait SearchHandler : Bits {
+    fn search(dic: &Dictionary<Self>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, mask_default: Self, ctx: &mut SearchContext);
 
-    fn into_iter(self) -> PieceCountBitSeqIter {
-        PieceCountBitSeqIter { val: self.val }
+    /// Returns `true` if `mask` can be proven not to lead to any answer.
+    fn is_dead_end(_dic: &Dictionary<Self>, _mask: &Self) -> bool {
+        false
     }
 }
 
-impl Iterator for PieceCountBitSeqIter {
-    type Item = usize;
+impl<T: FixedBits> SearchHandler for T {
+    fn search(dic: &Dictionary<Self>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, mask_default: Self, ctx: &mut SearchContext) {
+        let is_zero_one = rem_piece.iter().all(|&c| c <= 1);
 
-    fn next(&mut self) -> Option<usize> {
-        if self.val != 0 {
-            let ret = self.val.trailing_zeros();
-            self.val ^= 1u64 << (ret as u64);
-            Some(ret as usize)
+        let mut buf = RegionBuffer::new(dic);
+        if is_zero_one && rem_piece.len() <= 64 {
+            let mut rem_piece_bits = 0u64;
+            for (i, &c) in rem_piece.iter().enumerate() {
+                rem_piece_bits |= (c as u64) << (i as u64);
+            }
+            search_with_u64_rem_piece(dic, rem_piece_bits, answer_raw, mask_default, &mut buf, ctx);
         } else {
-            None
+            search_fixed(dic, rem_piece, answer_raw, mask_default, &mut buf, ctx);
         }
     }
+
+    fn is_dead_end(dic: &Dictionary<Self>, mask: &Self) -> bool {
+        isolated_cell_pruning(dic, *mask)
+    }
+}
+
+impl SearchHandler for Bitset {
+    fn search(dic: &Dictionary<Self>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, mask_default: Self, ctx: &mut SearchContext) {
+        let mut mask_default = mask_default;
+        let mut buf = RegionBuffer::new(dic);
+        search_generic(dic, rem_piece, answer_raw, &mut mask_default, &mut buf, ctx);
+    }
 }
 
-fn search_with_u64_rem_piece(dic: &Dictionary<u64>, rem_piece: u64, answer_raw: &mut Vec<(i32, i32)>, mask: u64, answers: &mut Answers) {
-    let pos = (!mask).trailing_zeros() as i32;
+fn search_with_u64_rem_piece<T: FixedBits>(dic: &Dictionary<T>, rem_piece: u64, answer_raw: &mut Vec<(i32, i32)>, mask: T, buf: &mut RegionBuffer<T>, ctx: &mut SearchContext) {
+    let pos = mask.lowest_unset_bit();
 
     if pos == dic.n_target_cells {
-        let mut rem_piece_as_vec = vec![0i32; dic.piece_count.len()];
-        for i in 0..rem_piece_as_vec.len() {
-            rem_piece_as_vec[i] = ((rem_piece >> i) & 1) as i32;
-        }
-        save_answer(dic, &mut rem_piece_as_vec, answer_raw, answers);
+        save_answer(dic, answer_raw, ctx);
         return;
     }
 
@@ -122,22 +334,25 @@ fn search_with_u64_rem_piece(dic: &Dictionary<u64>, rem_piece: u64, answer_raw:
         let pl = unsafe { dic.placements.get_unchecked(pos as usize).get_unchecked(i) };
         for j in 0..pl.len() {
             let m = unsafe { *pl.get_unchecked(j) };
-            answers.search_steps += 1;
-            if (mask & m) == 0 {
+            if ctx.step() { break; }
+            if mask.disjoint(&m) {
+                if dic.region_pruning && has_dead_region(dic, &mask.or(m), &m, buf, || reachable_volumes_u64(dic, rem_piece_orig ^ (1u64 << (i as u64)))) { continue; }
                 unsafe { *answer_raw.get_unchecked_mut(pos as usize) = (i as i32, j as i32); }
-                search_with_u64_rem_piece(dic, rem_piece_orig ^ (1u64 << (i as u64)), answer_raw, mask | m, answers);
+                search_with_u64_rem_piece(dic, rem_piece_orig ^ (1u64 << (i as u64)), answer_raw, mask.or(m), buf, ctx);
+                if ctx.terminated { break; }
             }
         }
+        if ctx.terminated { break; }
     }
 
     unsafe { *answer_raw.get_unchecked_mut(pos as usize) = (-1, -1); }
 }
 
-fn search(dic: &Dictionary<u64>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, mask: u64, answers: &mut Answers) {
-    let pos = (!mask).trailing_zeros() as i32;
+fn search_fixed<T: FixedBits>(dic: &Dictionary<T>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, mask: T, buf: &mut RegionBuffer<T>, ctx: &mut SearchContext) {
+    let pos = mask.lowest_unset_bit();
 
     if pos == dic.n_target_cells {
-        save_answer(dic, rem_piece, answer_raw, answers);
+        save_answer(dic, answer_raw, ctx);
         return;
     }
 
@@ -149,36 +364,132 @@ fn search(dic: &Dictionary<u64>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<
             let pl = unsafe { dic.placements.get_unchecked(pos as usize).get_unchecked(i) };
             for j in 0..pl.len() {
                 let m = unsafe { *pl.get_unchecked(j) };
-                answers.search_steps += 1;
-                if (mask & m) == 0 {
+                if ctx.step() { break; }
+                if mask.disjoint(&m) {
+                    if dic.region_pruning && has_dead_region(dic, &mask.or(m), &m, buf, || reachable_volumes(dic, rem_piece)) { continue; }
                     unsafe { *answer_raw.get_unchecked_mut(pos as usize) = (i as i32, j as i32); }
-                    search(dic, rem_piece, answer_raw, mask | m, answers);
+                    search_fixed(dic, rem_piece, answer_raw, mask.or(m), buf, ctx);
+                    if ctx.terminated { break; }
                 }
             }
             unsafe { *rem_piece.get_unchecked_mut(i) += 1 };
+            if ctx.terminated { break; }
         }
     }
 
     unsafe { *answer_raw.get_unchecked_mut(pos as usize) = (-1, -1); }
 }
 
-fn isolated_cell_pruning(dic: &Dictionary<u64>, mask: u64) -> bool {
+fn isolated_cell_pruning<T: FixedBits>(dic: &Dictionary<T>, mask: T) -> bool {
     dic.isolated_cell_pruning &&
-           (!mask
-         & ((mask << dic.isolated_cell_pruning_x_ofs) | dic.isolated_cell_pruning_x_mask_lo)
-         & ((mask >> dic.isolated_cell_pruning_x_ofs) | dic.isolated_cell_pruning_x_mask_hi)
-         & ((mask << dic.isolated_cell_pruning_y_ofs) | dic.isolated_cell_pruning_y_mask_lo)
-         & ((mask >> dic.isolated_cell_pruning_y_ofs) | dic.isolated_cell_pruning_y_mask_hi)
-         & ((mask << dic.isolated_cell_pruning_z_ofs) | dic.isolated_cell_pruning_z_mask_lo)
-         & ((mask >> dic.isolated_cell_pruning_z_ofs) | dic.isolated_cell_pruning_z_mask_hi)
-        ) != 0
+        !mask.not()
+            .and(mask.shl(dic.isolated_cell_pruning_x_ofs).or(dic.isolated_cell_pruning_x_mask_lo))
+            .and(mask.shr(dic.isolated_cell_pruning_x_ofs).or(dic.isolated_cell_pruning_x_mask_hi))
+            .and(mask.shl(dic.isolated_cell_pruning_y_ofs).or(dic.isolated_cell_pruning_y_mask_lo))
+            .and(mask.shr(dic.isolated_cell_pruning_y_ofs).or(dic.isolated_cell_pruning_y_mask_hi))
+            .and(mask.shl(dic.isolated_cell_pruning_z_ofs).or(dic.isolated_cell_pruning_z_mask_lo))
+            .and(mask.shr(dic.isolated_cell_pruning_z_ofs).or(dic.isolated_cell_pruning_z_mask_hi))
+            .is_empty()
+}
+
+/// Working space of `has_dead_region`, kept across calls to avoid allocations.
+struct RegionBuffer<T: Bits> {
+    border: T,
+    region: T,
+    frontier: T,
+    grown: T,
+}
+
+impl<T: Bits> RegionBuffer<T> {
+    fn new(dic: &Dictionary<T>) -> RegionBuffer<T> {
+        RegionBuffer {
+            border: dic.empty_mask(),
+            region: dic.empty_mask(),
+            frontier: dic.empty_mask(),
+            grown: dic.empty_mask(),
+        }
+    }
 }
 
-fn search_generic<T: Bits>(dic: &Dictionary<T>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, mask: &mut T, answers: &mut Answers) {
+/// Returns `true` if some region of empty cells adjacent to `placed`, the piece just placed, cannot be filled.
+///
+/// A region is dead if its volume is not a sum of volumes of the remaining pieces,
+/// of which bit `v` of `reachable()` tells whether `v` is such a sum.
+/// Regions larger than `dic.region_pruning_limit` are never regarded as dead.
+fn has_dead_region<T: Bits, F: Fn() -> u64>(dic: &Dictionary<T>, mask: &T, placed: &T, buf: &mut RegionBuffer<T>, reachable: F) -> bool {
+    let RegionBuffer { border, region, frontier, grown } = buf;
+    border.clear();
+    let mut c = placed.lowest_set_bit();
+    while c < dic.n_target_cells {
+        border.union_with(&dic.neighbor_mask[c as usize]);
+        c = placed.next_set_bit(c + 1);
+    }
+    border.subtract(mask);
+
+    while !border.is_empty() {
+        let start = border.lowest_set_bit();
+        region.clear();
+        region.set(start);
+        frontier.clone_from(region);
+
+        // grows `region` breadth-first until it is complete or turns out to be large
+        let mut is_large = false;
+        loop {
+            grown.clear();
+            let mut c = frontier.lowest_set_bit();
+            while c < dic.n_target_cells {
+                grown.union_with(&dic.neighbor_mask[c as usize]);
+                c = frontier.next_set_bit(c + 1);
+            }
+            grown.subtract(mask);
+            grown.subtract(region);
+            if grown.is_empty() { break; }
+            region.union_with(grown);
+            if region.count_ones() > dic.region_pruning_limit {
+                is_large = true;
+                break;
+            }
+            std::mem::swap(frontier, grown);
+        }
+        border.subtract(region);
+
+        if !is_large && (reachable() >> region.count_ones()) & 1 == 0 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Returns the set of volumes (up to 63) which are sums of volumes of the pieces in `rem_piece`, as a bit mask.
+fn reachable_volumes<T: Bits>(dic: &Dictionary<T>, rem_piece: &[i32]) -> u64 {
+    let mut ret = 1u64;
+    for (&count, &vol) in rem_piece.iter().zip(&dic.piece_volume) {
+        if vol == 0 || vol >= 64 { continue; }
+        for _ in 0..std::cmp::min(count, 63 / vol) {
+            ret |= ret << vol;
+        }
+    }
+    ret
+}
+
+fn reachable_volumes_u64<T: Bits>(dic: &Dictionary<T>, rem_piece: u64) -> u64 {
+    let mut ret = 1u64;
+    let mut rem_piece = rem_piece;
+    while rem_piece != 0 {
+        let i = rem_piece.trailing_zeros() as usize;
+        rem_piece ^= 1u64 << (i as u64);
+        let vol = dic.piece_volume[i];
+        if vol == 0 || vol >= 64 { continue; }
+        ret |= ret << vol;
+    }
+    ret
+}
+
+fn search_generic<T: Bits>(dic: &Dictionary<T>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, mask: &mut T, buf: &mut RegionBuffer<T>, ctx: &mut SearchContext) {
     let pos = mask.lowest_unset_bit();
 
     if pos == dic.n_target_cells {
-        save_answer(dic, rem_piece, answer_raw, answers);
+        save_answer(dic, answer_raw, ctx);
         return;
     }
 
@@ -188,66 +499,437 @@ fn search_generic<T: Bits>(dic: &Dictionary<T>, rem_piece: &mut Vec<i32>, answer
             let pl = unsafe { dic.placements.get_unchecked(pos as usize).get_unchecked(i) };
             for j in 0..pl.len() {
                 let m = unsafe { pl.get_unchecked(j) };
-                answers.search_steps += 1;
+                if ctx.step() { break; }
                 if mask.disjoint(m) {
-                    unsafe { *answer_raw.get_unchecked_mut(pos as usize) = (i as i32, j as i32); }
                     mask.update(m);
-                    search_generic(dic, rem_piece, answer_raw, mask, answers);
+                    if dic.region_pruning && has_dead_region(dic, mask, m, buf, || reachable_volumes(dic, rem_piece)) {
+                        mask.update(m);
+                        continue;
+                    }
+                    unsafe { *answer_raw.get_unchecked_mut(pos as usize) = (i as i32, j as i32); }
+                    search_generic(dic, rem_piece, answer_raw, mask, buf, ctx);
                     mask.update(m);
+                    if ctx.terminated { break; }
                 }
             }
             unsafe { *rem_piece.get_unchecked_mut(i) += 1 };
+            if ctx.terminated { break; }
         }
     }
 
     unsafe { *answer_raw.get_unchecked_mut(pos as usize) = (-1, -1); }
 }
 
-fn save_answer<T: Bits>(dic: &Dictionary<T>, rem_piece: &mut Vec<i32>, answer_raw: &mut Vec<(i32, i32)>, answers: &mut Answers) {
-    // check for uniqueness
-    let answer = Answer::from_answer(dic, answer_raw);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Instant;
+
+    #[test]
+    fn test_solve_with_streams_all_answers() {
+        let problem = Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 20, y: 3, z: 1 }));
+
+        let mut streamed = vec![];
+        let answers = solve_with(&problem, &SolveOptions::new(), |a| {
+            streamed.push(a.clone());
+            ControlFlow::Continue(())
+        }).unwrap();
+        assert_eq!(answers.count, 2);
+        assert!(answers.is_completed());
+        assert!(answers.answer.is_empty());
+
+        let collected = solve(&problem).unwrap();
+        assert_eq!(collected.count, 2);
+        assert!(streamed == collected.answer);
+    }
+
+    #[test]
+    fn test_solve_with_early_stop() {
+        let problem = Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 10, y: 6, z: 1 }));
+
+        let mut n_called = 0;
+        let answers = solve_with(&problem, &SolveOptions::new(), |_| {
+            n_called += 1;
+            if n_called == 10 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+        }).unwrap();
+        assert_eq!(n_called, 10);
+        assert_eq!(answers.count, 10);
+        assert_eq!(answers.status, SearchStatus::Stopped);
+
+        // other threads deliver no more answers after the stop
+        for _ in 0..5 {
+            let mut options = SolveOptions::new();
+            options.threads = 8;
+            let mut n_called = 0;
+            let answers = solve_with(&problem, &options, |_| {
+                n_called += 1;
+                if n_called == 20 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+            }).unwrap();
+            assert_eq!(n_called, 20);
+            assert_eq!(answers.count, 20);
+            assert_eq!(answers.status, SearchStatus::Stopped);
+        }
+    }
+
+    #[test]
+    fn test_parallel_solve() {
+        let problem = Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 10, y: 6, z: 1 }));
+
+        let sequential = solve(&problem).unwrap();
+        for &threads in &[2, 3, 8] {
+            let mut options = SolveOptions::new();
+            options.threads = threads;
+            let parallel = solve_with_options(&problem, &options).unwrap();
+
+            assert_eq!(parallel.count, 2339);
+            assert_eq!(parallel.search_steps, sequential.search_steps);
+            assert!(parallel.is_completed());
+            assert!(parallel.answer == sequential.answer);
+        }
+
+        // region pruning is applied while splitting tasks, too
+        let mut target = Shape::filled(Coord { x: 8, y: 8, z: 1 });
+        for &(x, y) in &[(3, 3), (3, 4), (4, 3), (4, 4)] {
+            target.set(Coord { x, y, z: 0 }, false);
+        }
+        let problem = Puzzle::with_names(library::pentominoes(), target);
+        assert!(Dictionary::<u64>::new(&problem).unwrap().region_pruning);
+        let sequential = solve(&problem).unwrap();
+        for &threads in &[2, 4] {
+            let mut options = SolveOptions::new();
+            options.threads = threads;
+            let parallel = solve_with_options(&problem, &options).unwrap();
+
+            assert_eq!(parallel.count, 65);
+            assert_eq!(parallel.search_steps, sequential.search_steps);
+        }
+    }
+
+    #[test]
+    fn test_cell_order() {
+        // the target is renumbered internally, but answers stay in the original coordinates
+        for &(x, y, z) in &[(10, 6, 1), (6, 10, 1), (1, 6, 10)] {
+            let problem = Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x, y, z }));
+            let answers = solve(&problem).unwrap();
+            assert_eq!(answers.count, 2339);
+            for a in &answers.answer {
+                assert_eq!(a.size(), Coord { x, y, z });
+                for cd in a.size() {
+                    assert!(a[cd].0 >= 0);
+                }
+            }
+        }
+
+        let mut target = Shape::filled(Coord { x: 8, y: 8, z: 1 });
+        for &(x, y) in &[(3, 3), (3, 4), (4, 3), (4, 4)] {
+            target.set(Coord { x, y, z: 0 }, false);
+        }
+        let problem = Puzzle::with_names(library::pentominoes(), target);
+        assert_eq!(solve(&problem).unwrap().count, 65);
+    }
 
-    for i in 1..24 {
-        if (dic.target_symmetry & (1u64 << i)) != 0 {
-            let mut answer_rot = answer.trans(ROTATIONS[i]);
-            answer_rot.reindex(&dic.piece_count, rem_piece);
-            if answer > answer_rot {
-                return;
+    #[test]
+    fn test_dead_region() {
+        fn check<T: Bits>() {
+            // a piece placed at x = 3..6 of a 1x7 strip leaves a region of 3 cells
+            let pieces = vec![
+                (Shape::filled(Coord { x: 1, y: 1, z: 1 }), 1),
+                (Shape::filled(Coord { x: 2, y: 1, z: 1 }), 1),
+                (Shape::filled(Coord { x: 4, y: 1, z: 1 }), 1),
+            ];
+            let dic = Dictionary::<T>::new(&Puzzle::new(pieces, Shape::filled(Coord { x: 7, y: 1, z: 1 }))).unwrap();
+            assert!(dic.region_pruning);
+
+            let mut placed = dic.empty_mask();
+            for x in 3..7 {
+                placed.set(dic.cell_id(Coord { x, y: 0, z: 0 }));
             }
+            let mut buf = RegionBuffer::new(&dic);
+            assert!(!has_dead_region(&dic, &placed, &placed, &mut buf, || reachable_volumes(&dic, &[1, 1, 0])));
+            assert!(has_dead_region(&dic, &placed, &placed, &mut buf, || reachable_volumes(&dic, &[0, 1, 0])));
         }
+        check::<u64>();
+        check::<Bitset>();
     }
 
-    // check for mirror flips?
-    let mut is_mirror_ok = true;
-    for i in 0..rem_piece.len() {
-        let n_used = dic.piece_count[i] - rem_piece[i];
-        if dic.mirror_pair[i] == -1 || dic.piece_count[dic.mirror_pair[i] as usize] < n_used {
-            is_mirror_ok = false;
-            break;
+    #[test]
+    fn test_bits_types() {
+        let problem = Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 3, y: 20, z: 1 }));
+        let callback: &AnswerCallback = &|_, _, _| ControlFlow::Continue(());
+        let options = SolveOptions::new();
+
+        let expected = solve_typed::<u64>(&problem, &options, callback).unwrap();
+        assert_eq!(expected.count, 2);
+        for answers in [
+            solve_typed::<u128>(&problem, &options, callback).unwrap(),
+            solve_typed::<[u64; 3]>(&problem, &options, callback).unwrap(),
+            solve_typed::<[u64; 4]>(&problem, &options, callback).unwrap(),
+        ] {
+            assert_eq!(answers.count, expected.count);
+            assert_eq!(answers.search_steps, expected.search_steps);
         }
+        assert_eq!(solve_typed::<Bitset>(&problem, &options, callback).unwrap().count, expected.count);
+    }
+
+    #[test]
+    fn test_malformed_puzzles() {
+        let domino = Shape::filled(Coord { x: 2, y: 1, z: 1 });
+
+        assert_eq!(Shape::from_grid(&["##", "#"]).err(), Some(PolymateError::RaggedGrid));
+        assert_eq!(Shape::from_grid(&[]).unwrap().volume(), 0);
+
+        let problem = Puzzle::new(vec![(domino.clone(), 1)], Shape::new(Coord { x: 2, y: 2, z: 1 }));
+        assert_eq!(solve(&problem).err(), Some(PolymateError::EmptyTarget));
+
+        let problem = Puzzle::new(vec![(domino.clone(), 2), (Shape::new(Coord { x: 1, y: 1, z: 1 }), 1)], Shape::filled(Coord { x: 2, y: 2, z: 1 }));
+        assert_eq!(solve(&problem).err(), Some(PolymateError::ZeroVolumePiece(1)));
+
+        let problem = Puzzle::new(vec![(domino.clone(), 2), (Shape::filled(Coord { x: 5, y: 1, z: 1 }), 1)], Shape::filled(Coord { x: 2, y: 2, z: 1 }));
+        assert_eq!(solve(&problem).err(), Some(PolymateError::PieceLargerThanTarget(1)));
+
+        let problem = Puzzle::new(vec![(domino.clone(), 1)], Shape::filled(Coord { x: 2, y: 2, z: 1 }));
+        assert_eq!(solve(&problem).err(), Some(PolymateError::VolumeMismatch { pieces: 2, target: 4 }));
+
+        let pieces = vec![(Shape::filled(Coord { x: 2, y: 2, z: 1 }), 1), (domino.clone(), 1), (Shape::filled(Coord { x: 1, y: 1, z: 1 }), -1)];
+        let problem = Puzzle::new(pieces, Shape::filled(Coord { x: 2, y: 2, z: 1 }));
+        assert_eq!(solve(&problem).err(), Some(PolymateError::NegativePieceCount(2)));
+
+        let problem = Puzzle::new(vec![(domino.clone(), 40)], Shape::filled(Coord { x: 10, y: 8, z: 1 }));
+        assert_eq!(Dictionary::<u64>::new(&problem).err(), Some(PolymateError::CapacityExceeded { cells: 80, capacity: 64 }));
+
+        // having no answer is not an error
+        let mut target = Shape::filled(Coord { x: 4, y: 4, z: 1 });
+        target.set(Coord { x: 0, y: 0, z: 0 }, false);
+        target.set(Coord { x: 3, y: 3, z: 0 }, false);
+        let problem = Puzzle::new(vec![(domino, 7)], target);
+        assert_eq!(solve(&problem).unwrap().count, 0);
     }
 
-    if is_mirror_ok {
-        for i in 24..48 {
-            if (dic.target_symmetry & (1u64 << i)) != 0 {
-                let mut answer_rot = answer.trans(TRANSFORMATIONS[i]);
-                answer_rot.mirror(&dic.mirror_pair);
-                answer_rot.reindex(&dic.piece_count, rem_piece);
-                if answer > answer_rot {
-                    return;
+    #[test]
+    fn test_orientation_policies() {
+        let one_sided = |names: &str, policy: OrientationPolicy, target: Shape| {
+            let pieces = library::one_sided_tetrominoes().into_iter()
+                .map(|(name, shape, _)| { let c = names.matches(&name[..]).count() as i32; (name, shape, c) })
+                .filter(|p| p.2 > 0)
+                .collect::<Vec<_>>();
+            let mut problem = Puzzle::with_names(pieces, target);
+            problem.orientations = vec![policy; problem.pieces.len()];
+            solve(&problem).unwrap().count
+        };
+        let rect = |x, y| Shape::filled(Coord { x, y, z: 1 });
+
+        // reflections of the target swap mirror images of pieces
+        assert_eq!(one_sided("IJLO", OrientationPolicy::InPlane, rect(4, 4)), 2);
+        assert_eq!(one_sided("JJLL", OrientationPolicy::InPlane, rect(4, 4)), 1);
+        assert_eq!(one_sided("IIJJLL", OrientationPolicy::InPlane, rect(6, 4)), 19);
+        assert_eq!(one_sided("IIJLSZ", OrientationPolicy::InPlane, rect(6, 4)), 1);
+        assert_eq!(one_sided("LL", OrientationPolicy::InPlane, rect(4, 2)), 1);
+        assert_eq!(one_sided("JL", OrientationPolicy::InPlane, rect(4, 2)), 0);
+        assert_eq!(one_sided("JL", OrientationPolicy::Rotations, rect(4, 2)), 1);
+        assert_eq!(one_sided("JL", OrientationPolicy::Free, rect(4, 2)), 1);
+        assert_eq!(one_sided("LL", OrientationPolicy::Fixed, rect(4, 2)), 0);
+        assert_eq!(one_sided("II", OrientationPolicy::Fixed, rect(4, 2)), 1);
+        assert_eq!(one_sided("II", OrientationPolicy::Fixed, rect(2, 4)), 0);
+        assert_eq!(one_sided("L", OrientationPolicy::InPlane, Shape::from_grid(&["###", "..#"]).unwrap()), 0);
+
+        // the 3x20 pentomino puzzle has the same answers whether or not the pieces are mirrored
+        let mut problem = Puzzle::with_names(library::pentominoes(), rect(20, 3));
+        problem.orientations = vec![OrientationPolicy::Free; 12];
+        assert_eq!(solve(&problem).unwrap().count, 2);
+        problem.orientations[0] = OrientationPolicy::Explicit(vec![Transformation::id()]);
+        assert_eq!(solve(&problem).unwrap().count, 0);
+        problem.orientations[0] = OrientationPolicy::Explicit(vec![Transformation::id().rotate_z_axis()]);
+        assert_eq!(solve(&problem).unwrap().count, 2);
+
+        problem.orientations.pop();
+        assert_eq!(solve(&problem).err(), Some(PolymateError::OrientationsMismatch { pieces: 12, orientations: 11 }));
+        problem.orientations.push(OrientationPolicy::Rotations);
+        problem.piece_names.pop();
+        assert_eq!(solve(&problem).err(), Some(PolymateError::PieceNamesMismatch { pieces: 12, names: 11 }));
+    }
+
+    #[test]
+    fn test_constraints() {
+        let domino = Shape::filled(Coord { x: 2, y: 1, z: 1 });
+        let fixed = |piece, offset| FixedPlacement { piece, transformation: Transformation::id(), offset };
+        let origin = Coord { x: 0, y: 0, z: 0 };
+
+        // 5 tilings of 2x4 by dominoes, 2 of which have a horizontal domino at a corner
+        let mut problem = Puzzle::new(vec![(domino.clone(), 4)], Shape::filled(Coord { x: 4, y: 2, z: 1 }));
+        problem.fixed.push(fixed(0, origin));
+        let answers = solve(&problem).unwrap();
+        assert_eq!(answers.count, 2);
+        assert!(answers.answer.iter().all(|a| a[origin].0 == 0 && a[Coord { x: 1, y: 0, z: 0 }] == a[origin]));
+        problem.blocked.push(Coord { x: 3, y: 1, z: 0 });
+        problem.pieces.push((Shape::filled(Coord { x: 1, y: 1, z: 1 }), 1));
+        problem.piece_names.push("B".to_string());
+        problem.orientations.push(OrientationPolicy::Rotations);
+        for &backend in &[Backend::Bitmask, Backend::MostConstrained, Backend::DancingLinks] {
+            let mut options = SolveOptions::new();
+            options.backend = backend;
+            assert_eq!(solve_with_options(&problem, &options).unwrap().count, 3);
+        }
+
+        // blocking the center of 8x8 is the same as removing it
+        let mut problem = Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 8, y: 8, z: 1 }));
+        for x in 3..5 {
+            for y in 3..5 {
+                problem.blocked.push(Coord { x, y, z: 0 });
+            }
+        }
+        let answers = solve(&problem).unwrap();
+        assert_eq!(answers.count, 65);
+        assert_eq!(answers.answer[0][Coord { x: 3, y: 4, z: 0 }], BLOCKED);
+
+        // fixing a piece of an answer keeps the answer
+        let mut problem = Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 20, y: 3, z: 1 }));
+        let answer = solve(&problem).unwrap().answer.swap_remove(0);
+        let x_cells = Coord { x: 20, y: 3, z: 1 }.into_iter().filter(|&cd| answer[cd].0 == 9).collect::<Vec<_>>();
+        problem.fixed.push(fixed(9, x_cells[0] - Coord { x: 0, y: 1, z: 0 }));
+        let answers = solve(&problem).unwrap();
+        assert!(answers.answer.contains(&answer));
+
+        // nothing is left for the search
+        let mut problem = Puzzle::new(vec![(domino.clone(), 1)], domino.clone());
+        problem.fixed.push(fixed(0, origin));
+        assert_eq!(solve(&problem).unwrap().count, 1);
+
+        let mut problem = Puzzle::new(vec![(domino.clone(), 2)], Shape::filled(Coord { x: 2, y: 2, z: 1 }));
+        problem.fixed.push(fixed(0, Coord { x: 1, y: 0, z: 0 }));
+        assert_eq!(solve(&problem).err(), Some(PolymateError::InvalidFixedPlacement(0)));
+        problem.fixed = vec![fixed(0, origin), fixed(0, origin)];
+        assert_eq!(solve(&problem).err(), Some(PolymateError::InvalidFixedPlacement(1)));
+        problem.fixed = vec![fixed(0, origin), fixed(0, Coord { x: 0, y: 1, z: 0 }), fixed(0, origin)];
+        assert_eq!(solve(&problem).err(), Some(PolymateError::InvalidFixedPlacement(2)));
+        problem.fixed = vec![fixed(0, origin)];
+        problem.blocked = vec![origin];
+        assert_eq!(solve(&problem).err(), Some(PolymateError::InvalidBlockedCell(origin)));
+        problem.fixed = vec![];
+        problem.blocked = vec![origin, origin, Coord { x: 1, y: 0, z: 0 }];
+        assert_eq!(solve(&problem).unwrap().count, 1);
+    }
+
+    #[test]
+    fn test_backends() {
+        let problems = vec![
+            (Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 20, y: 3, z: 1 })), 2),
+            (Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 5, y: 4, z: 1 })), 50),
+            (Puzzle::new(vec![(Shape::from_grid(&["#.", "##"]).unwrap(), 8)], Shape::filled(Coord { x: 4, y: 3, z: 2 })), 621),
+        ];
+        for (problem, expected) in &problems {
+            let mut results = vec![];
+            for &backend in &[Backend::Bitmask, Backend::MostConstrained, Backend::DancingLinks] {
+                for &threads in &[1, 3] {
+                    let mut options = SolveOptions::new();
+                    options.backend = backend;
+                    options.threads = threads;
+                    let mut answers = solve_with_options(problem, &options).unwrap();
+                    assert!(answers.is_completed());
+                    answers.answer.sort();
+                    results.push(answers);
                 }
             }
+            for res in &results {
+                assert_eq!(res.count, *expected);
+                assert!(res.answer == results[0].answer);
+            }
         }
+
+        let mut options = SolveOptions::new();
+        options.backend = Backend::DancingLinks;
+        options.max_search_steps = Some(1000);
+        let answers = solve_with_options(&problems[0].0, &options).unwrap();
+        assert_eq!(answers.search_steps, 1000);
+        assert_eq!(answers.status, SearchStatus::StepLimitReached);
     }
 
-    // save answer
-    answers.count += 1;
+    #[test]
+    fn test_special_pieces() {
+        // spare pieces, and pieces with two copies each
+        let tetrominoes = library::tetrominoes().into_iter().map(|(_, shape, _)| (shape, 2)).collect::<Vec<_>>();
+        let problems = vec![
+            (Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 5, y: 5, z: 1 })), 107),
+            (Puzzle::new(tetrominoes.clone(), Shape::filled(Coord { x: 8, y: 2, z: 1 })), 7),
+            (Puzzle::new(tetrominoes, Shape::filled(Coord { x: 6, y: 4, z: 1 })), 150),
+        ];
+        for (problem, count) in &problems {
+            let dic = Dictionary::<u64>::new(problem).unwrap();
+            assert!(dic.initial_placement_id.len() > 1);
+            for &backend in &[Backend::Bitmask, Backend::MostConstrained, Backend::DancingLinks] {
+                let mut options = SolveOptions::new();
+                options.backend = backend;
+                assert_eq!(solve_with_options(problem, &options).unwrap().count, *count);
+            }
+        }
+    }
 
-    let save = match answers.save_limit {
-        Some(lim) => answers.count <= lim as u64,
-        None => true,
-    };
-    if save {
-        answers.answer.push(answer);
+    #[test]
+    fn test_count_symmetric() {
+        let problem = Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 20, y: 3, z: 1 }));
+        let answers = solve(&problem).unwrap();
+        assert_eq!(answers.raw_count, None);
+        assert!(answers.orbit_size.is_empty());
+
+        let mut options = SolveOptions::new();
+        options.count_symmetric = true;
+        let answers = solve_with_options(&problem, &options).unwrap();
+        assert_eq!(answers.count, 2);
+        assert_eq!(answers.raw_count, Some(8));
+        assert_eq!(answers.orbit_size, vec![4, 4]);
+
+        // symmetric answers, spare pieces, and copies of pieces (checked by brute force)
+        let tetrominoes = library::tetrominoes().into_iter().map(|(_, shape, _)| (shape, 2)).collect::<Vec<_>>();
+        let problems = vec![
+            (Puzzle::new(tetrominoes, Shape::filled(Coord { x: 4, y: 4, z: 1 })), 16, 102),
+            (Puzzle::new(vec![(Shape::filled(Coord { x: 2, y: 1, z: 1 }), 3), (Shape::filled(Coord { x: 3, y: 1, z: 1 }), 2), (Shape::filled(Coord { x: 1, y: 1, z: 1 }), 2)], Shape::filled(Coord { x: 3, y: 3, z: 1 })), 12, 76),
+        ];
+        for (problem, count, raw_count) in &problems {
+            for &backend in &[Backend::Bitmask, Backend::MostConstrained, Backend::DancingLinks] {
+                options.backend = backend;
+                let answers = solve_with_options(problem, &options).unwrap();
+                assert_eq!(answers.count, *count);
+                assert_eq!(answers.raw_count, Some(*raw_count));
+                assert_eq!(answers.orbit_size.iter().sum::<u64>(), *raw_count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_limits() {
+        let problem = Puzzle::with_names(library::pentominoes(), Shape::filled(Coord { x: 10, y: 6, z: 1 }));
+
+        let mut options = SolveOptions::new();
+        options.max_search_steps = Some(12345);
+        let answers = solve_with_options(&problem, &options).unwrap();
+        assert_eq!(answers.search_steps, 12345);
+        assert_eq!(answers.status, SearchStatus::StepLimitReached);
+        assert!(!answers.is_completed());
+
+        let mut options = SolveOptions::new();
+        options.deadline = Some(Instant::now());
+        let answers = solve_with_options(&problem, &options).unwrap();
+        assert_eq!(answers.count, 0);
+        assert_eq!(answers.status, SearchStatus::TimedOut);
+
+        // a flag set beforehand stops the search before the first step
+        let cancel = Arc::new(AtomicBool::new(true));
+        let mut options = SolveOptions::new();
+        options.cancel = Some(cancel.clone());
+        let answers = solve_with_options(&problem, &options).unwrap();
+        assert_eq!((answers.count, answers.search_steps), (0, 0));
+        assert_eq!(answers.status, SearchStatus::Cancelled);
+
+        // a flag set from the callback is noticed within one check interval after the first answer
+        let first = solve_with(&problem, &SolveOptions::new(), |_| ControlFlow::Break(())).unwrap();
+        cancel.store(false, Ordering::Relaxed);
+        let answers = solve_with(&problem, &options, |_| {
+            cancel.store(true, Ordering::Relaxed);
+            ControlFlow::Continue(())
+        }).unwrap();
+        assert!(answers.search_steps <= first.search_steps + INTERRUPT_CHECK_INTERVAL);
+        assert_eq!(answers.status, SearchStatus::Cancelled);
     }
 }
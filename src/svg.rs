@@ -0,0 +1,219 @@
+//! Rendering of answers as SVG images.
+
+use super::*;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Options controlling `Answer::to_svg`. All lengths are in pixels.
+#[derive(Clone)]
+pub struct SvgOptions {
+    pub cell_size: i32,
+    /// Width of the borders between different pieces and around the answer.
+    pub border_width: i32,
+    /// Width of the lines between cells of the same piece (`0` for none).
+    pub grid_width: i32,
+    /// Space between the layers of a 3D answer, which are laid out from left to right in the order of z.
+    pub layer_gap: i32,
+    /// Fill color of each piece type; copies of a piece share its color.
+    /// Piece `i` is painted in `colors[i % colors.len()]`, or in the default palette if `colors` is empty.
+    pub colors: Vec<String>,
+    pub blocked_color: String,
+    pub border_color: String,
+}
+
+/// The default colors of pieces.
+const PALETTE: [&str; 12] = [
+    "#e6194b", "#3cb44b", "#ffe119", "#4363d8", "#f58231", "#911eb4",
+    "#46f0f0", "#f032e6", "#bcf60c", "#fabebe", "#008080", "#e6beff",
+];
+
+impl Default for SvgOptions {
+    fn default() -> SvgOptions {
+        SvgOptions {
+            cell_size: 20,
+            border_width: 3,
+            grid_width: 1,
+            layer_gap: 20,
+            colors: PALETTE.iter().map(|c| c.to_string()).collect(),
+            blocked_color: "#404040".to_string(),
+            border_color: "#000000".to_string(),
+        }
+    }
+}
+
+impl SvgOptions {
+    pub fn new() -> SvgOptions {
+        SvgOptions::default()
+    }
+}
+
+/// Returns the outline of the cells `cells` (in cell units) as an SVG path.
+///
+/// Boundary edges are directed clockwise around the cells, so outer boundaries and holes are closed loops,
+/// and the loops are traced with the corners between collinear edges dropped.
+fn outline_path(cells: &[(i32, i32)], origin: (i32, i32), cell_size: i32) -> String {
+    let contains = |x, y| cells.contains(&(x, y));
+    let mut edges: BTreeMap<(i32, i32), Vec<(i32, i32)>> = BTreeMap::new();
+    for &(x, y) in cells {
+        let sides = [
+            ((x, y - 1), (x, y), (x + 1, y)),
+            ((x + 1, y), (x + 1, y), (x + 1, y + 1)),
+            ((x, y + 1), (x + 1, y + 1), (x, y + 1)),
+            ((x - 1, y), (x, y + 1), (x, y)),
+        ];
+        for &((nx, ny), from, to) in &sides {
+            if !contains(nx, ny) {
+                edges.entry(from).or_default().push(to);
+            }
+        }
+    }
+
+    let mut loops = vec![];
+    while let Some((&start, _)) = edges.iter().next() {
+        let mut points = vec![start];
+        let mut cur = start;
+        loop {
+            let next = {
+                let outgoing = edges.get_mut(&cur).unwrap();
+                let next = outgoing.pop().unwrap();
+                if outgoing.is_empty() {
+                    edges.remove(&cur);
+                }
+                next
+            };
+            if next == start {
+                break;
+            }
+            points.push(next);
+            cur = next;
+        }
+
+        // drop the points in the middle of straight lines
+        let n = points.len();
+        let is_corner = |i: usize| {
+            let (p, c, q) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+            (c.0 - p.0) * (q.1 - c.1) != (c.1 - p.1) * (q.0 - c.0)
+        };
+        let corners = (0..n).filter(|&i| is_corner(i)).map(|i| points[i]).collect::<Vec<_>>();
+        let mut path = String::new();
+        for (i, &(x, y)) in corners.iter().enumerate() {
+            let _ = write!(path, "{}{},{} ", if i == 0 { "M" } else { "L" }, origin.0 + x * cell_size, origin.1 + y * cell_size);
+        }
+        path.push('Z');
+        loops.push(path);
+    }
+    loops.join(" ")
+}
+
+impl Answer {
+    /// Renders `self` as an SVG image.
+    ///
+    /// Each piece instance (a `(piece, index)` pair) is drawn as a filled polygon, with thick borders between
+    /// different instances. Blocked cells are painted in `options.blocked_color`, and unfilled cells are left blank.
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let size = self.size();
+        let cs = options.cell_size;
+        let margin = options.border_width;
+        let layer_width = size.x * cs;
+        let width = size.z * layer_width + (size.z - 1).max(0) * options.layer_gap + 2 * margin;
+        let height = size.y * cs + 2 * margin;
+
+        let mut fills = String::new();
+        let mut grid = String::new();
+        let mut borders = String::new();
+        for z in 0..size.z {
+            let origin = (margin + z * (layer_width + options.layer_gap), margin);
+
+            // cells of each instance in this layer
+            let mut instances: BTreeMap<(i32, i32), Vec<(i32, i32)>> = BTreeMap::new();
+            for y in 0..size.y {
+                for x in 0..size.x {
+                    let val = self[Coord { x, y, z }];
+                    if val != UNFILLED {
+                        instances.entry(val).or_default().push((x, y));
+                    }
+                }
+            }
+
+            for (&(piece, _), cells) in &instances {
+                let color = if piece < 0 {
+                    &options.blocked_color[..]
+                } else if options.colors.is_empty() {
+                    PALETTE[piece as usize % PALETTE.len()]
+                } else {
+                    &options.colors[piece as usize % options.colors.len()]
+                };
+                let path = outline_path(cells, origin, cs);
+                let _ = writeln!(fills, "<path d=\"{}\" fill=\"{}\" fill-rule=\"evenodd\"/>", path, color);
+                let _ = writeln!(borders, "<path d=\"{}\"/>", path);
+
+                // lines between cells of the same instance
+                if options.grid_width > 0 {
+                    for &(x, y) in cells {
+                        let (px, py) = (origin.0 + x * cs, origin.1 + y * cs);
+                        if cells.contains(&(x + 1, y)) {
+                            let _ = writeln!(grid, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>", px + cs, py, px + cs, py + cs);
+                        }
+                        if cells.contains(&(x, y + 1)) {
+                            let _ = writeln!(grid, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>", px, py + cs, px + cs, py + cs);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut ret = String::new();
+        let _ = writeln!(ret, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">", width, height, width, height);
+        ret.push_str(&fills);
+        if !grid.is_empty() {
+            let _ = writeln!(ret, "<g stroke=\"{}\" stroke-width=\"{}\" stroke-opacity=\"0.3\">", options.border_color, options.grid_width);
+            ret.push_str(&grid);
+            ret.push_str("</g>\n");
+        }
+        let _ = writeln!(ret, "<g fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linejoin=\"round\" stroke-linecap=\"round\">", options.border_color, options.border_width);
+        ret.push_str(&borders);
+        ret.push_str("</g>\n</svg>\n");
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_svg() {
+        // two L-trominoes and a blocked cell in a 2x2x2 box
+        let mut answer = Answer::new(Coord { x: 2, y: 2, z: 2 });
+        for &(x, y, z, val) in &[(0, 0, 0, (0, 0)), (1, 0, 0, (0, 0)), (0, 1, 0, (0, 0)), (1, 1, 0, BLOCKED),
+                                  (0, 0, 1, (1, 0)), (1, 0, 1, (1, 0)), (1, 1, 1, (1, 0)), (0, 1, 1, UNFILLED)] {
+            answer[Coord { x, y, z }] = val;
+        }
+        let mut options = SvgOptions::new();
+        options.colors = vec!["red".to_string(), "blue".to_string()];
+        let svg = answer.to_svg(&options);
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"106\" height=\"46\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<path").count(), 6);
+        assert_eq!(svg.matches("<line").count(), 4);
+        assert!(svg.contains("<path d=\"M3,3 L43,3 L43,23 L23,23 L23,43 L3,43 Z\" fill=\"red\""));
+        assert!(svg.contains("fill=\"blue\""));
+        assert!(svg.contains(&format!("fill=\"{}\"", options.blocked_color)));
+        options.colors.clear();
+        let svg = answer.to_svg(&options);
+        assert!(svg.contains(&format!("fill=\"{}\"", PALETTE[1])));
+
+        // a ring has its hole as another loop
+        let size = Coord { x: 3, y: 3, z: 1 };
+        let mut answer = Answer::new(size);
+        for cd in size {
+            if cd != (Coord { x: 1, y: 1, z: 0 }) {
+                answer[cd] = (0, 0);
+            }
+        }
+        let svg = answer.to_svg(&SvgOptions::new());
+        assert!(svg.contains("<path d=\"M3,3 L63,3 L63,63 L3,63 Z M23,23 L23,43 L43,43 L43,23 Z\""));
+    }
+}
@@ -1,6 +1,6 @@
 use super::*;
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Transformation {
     origin: [i32; 3],
 }
@@ -14,9 +14,9 @@ impl Transformation {
     pub fn trans_rect(&self, size: Coord) -> Coord {
         let dim = [size.x, size.y, size.z];
         Coord {
-            x: dim[if self.origin[0] >= 0 { self.origin[0] } else { (!self.origin[0]) } as usize],
-            y: dim[if self.origin[1] >= 0 { self.origin[1] } else { (!self.origin[1]) } as usize],
-            z: dim[if self.origin[2] >= 0 { self.origin[2] } else { (!self.origin[2]) } as usize],
+            x: dim[if self.origin[0] >= 0 { self.origin[0] } else { !self.origin[0] } as usize],
+            y: dim[if self.origin[1] >= 0 { self.origin[1] } else { !self.origin[1] } as usize],
+            z: dim[if self.origin[2] >= 0 { self.origin[2] } else { !self.origin[2] } as usize],
         }
     }
     pub fn trans_point(&self, p: Coord, rect: Coord) -> Coord {
@@ -28,6 +28,10 @@ impl Transformation {
             z: if self.origin[2] >= 0 { pt[self.origin[2] as usize] } else { dim[!self.origin[2] as usize] - pt[!self.origin[2] as usize] - 1 },
         }
     }
+    /// Returns `true` if `self` keeps the z-axis fixed, i.e. maps the xy-plane onto itself (possibly reflecting it within the plane).
+    pub fn is_in_plane(&self) -> bool {
+        self.origin[2] == 2
+    }
     pub fn flip_x(&self) -> Transformation {
         Transformation {
             origin: [